@@ -28,4 +28,9 @@ impl BingoState {
             None
         }
     }
+
+    // 最後に出た番号
+    pub fn last(&self) -> Option<u8> {
+        self.index.checked_sub(1).and_then(|i| self.numbers.get(i).copied())
+    }
 }
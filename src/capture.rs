@@ -0,0 +1,260 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ToExtents,
+    prelude::*,
+    render::{
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use crate::led::LedScreen;
+use crate::loader::Config;
+
+// 出力する画素一つあたりに描画するピクセル数（縦横、平均して画素の色にする）
+const SAMPLES: f32 = 4.0;
+
+// カメラの描画先にする画像
+pub fn render_target(size: UVec2, images: &mut Assets<Image>) -> Handle<Image> {
+    let mut image = Image::new_uninit(
+        size.to_extents(),
+        TextureDimension::D2,
+        TextureFormat::bevy_default(),
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    images.add(image)
+}
+
+// メインのウィンドウの内容を描画した画像（LED の表示ではその画像、それ以外は同じ内容を別に描画する）
+#[derive(Resource)]
+pub struct Capture {
+    pub image: Handle<Image>,
+    pub size: UVec2,
+    pub mirror: bool,     // spawn_cameras でこの画像用のカメラを作る
+    pub continuous: bool, // 出力がある場合は毎フレーム読み出す
+    pub requested: bool,  // このフレームで読み出しが必要か（スナップショットなど、毎フレーム立て直す）
+    reading: bool,
+    entity: Entity,
+}
+
+impl Capture {
+    // 出力（[[outputs]]）がなく、ウィンドウに表示する場合は None（size は export で指定された大きさ）
+    pub fn new(
+        config: &Config,
+        led: Option<&LedScreen>,
+        headless: bool,
+        size: Option<UVec2>,
+        images: &mut Assets<Image>,
+    ) -> Option<Self> {
+        if config.outputs.is_empty() && !headless {
+            return None;
+        }
+        let continuous = !config.outputs.is_empty();
+        let (image, size, mirror) = match led {
+            Some(led) => (led.0.clone(), images.get(&led.0).map_or(UVec2::ONE, Image::size), false),
+            None => {
+                // ウィンドウがない場合は画面と同じ解像度、それ以外は最も細かい出力に合わせた解像度（画面の大きさを超えない）
+                let screen = config.screen_size();
+                let scale = if headless {
+                    1.0
+                } else {
+                    config
+                        .outputs
+                        .iter()
+                        .map(|output| {
+                            let area = output.area(screen).size();
+                            let pixels = Vec2::new(output.width as f32, output.height as f32) * SAMPLES;
+                            (pixels / area).max_element()
+                        })
+                        .fold(0.0, f32::max)
+                        .min(1.0)
+                };
+                let size = size.unwrap_or_else(|| (screen * scale).ceil().as_uvec2().max(UVec2::ONE));
+                (render_target(size, images), size, true)
+            }
+        };
+        Some(Self {
+            image,
+            size,
+            mirror,
+            continuous,
+            requested: false,
+            reading: false,
+            entity: Entity::PLACEHOLDER,
+        })
+    }
+
+    pub fn mirror_image(&self) -> Option<&Handle<Image>> {
+        self.mirror.then_some(&self.image)
+    }
+
+    // 読み出しの結果（ReadbackComplete）を受け取るエンティティ
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
+// 最後に読み出した画像（RGBA、行の詰め物は取り除いてある）
+#[derive(Resource, Default)]
+pub struct CapturedFrame {
+    pub size: UVec2,
+    pub data: Vec<u8>,
+    pub count: u64, // 読み出した回数（新しいフレームかどうかの判定に使う）
+}
+
+impl CapturedFrame {
+    // 画面（screen の大きさ）の area を width × height 画素に縮小した色（左上から行ごと）
+    pub fn sample(&self, screen: Vec2, area: Rect, width: u32, height: u32) -> Vec<[u8; 3]> {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        if self.data.is_empty() {
+            pixels.resize((width * height) as usize, [0; 3]);
+            return pixels;
+        }
+        let scale = self.size.as_vec2() / screen;
+        let cell = area.size() / Vec2::new(width as f32, height as f32);
+        for y in 0..height {
+            for x in 0..width {
+                let min = (area.min + cell * Vec2::new(x as f32, y as f32)) * scale;
+                let max = min + cell * scale;
+                pixels.push(self.average(min, max));
+            }
+        }
+        pixels
+    }
+
+    fn average(&self, min: Vec2, max: Vec2) -> [u8; 3] {
+        let min = min.floor().as_uvec2().min(self.size - UVec2::ONE);
+        let max = max.ceil().as_uvec2().clamp(min + UVec2::ONE, self.size);
+        let mut sum = [0u32; 3];
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let i = ((y * self.size.x + x) * 4) as usize;
+                for (c, total) in sum.iter_mut().enumerate() {
+                    *total += self.data[i + c] as u32;
+                }
+            }
+        }
+        let n = (max.x - min.x) * (max.y - min.y);
+        sum.map(|total| (total / n) as u8)
+    }
+}
+
+// RGBA を不透明の RGB（ウィンドウに表示される色）にする
+pub fn rgb(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect()
+}
+
+// 読み出したデータから行の詰め物を取り除く（行は 256 バイト単位に揃えて読み出される）
+pub fn unpad(data: &[u8], size: UVec2) -> Option<Vec<u8>> {
+    let row = (size.x * 4) as usize;
+    let padded_row = row.next_multiple_of(256);
+    if data.len() < padded_row * (size.y as usize - 1) + row {
+        return None;
+    }
+    Some((0..size.y as usize).flat_map(|y| &data[y * padded_row..y * padded_row + row]).copied().collect())
+}
+
+// 画像を読み出すエンティティ（出力がなければ readback_system が必要な間だけ読み出す）
+pub fn spawn_readback(cmds: &mut Commands, capture: &mut Capture) {
+    let size = capture.size;
+    let mut entity = cmds.spawn_empty();
+    if capture.continuous {
+        entity.insert(Readback::texture(capture.image.clone()));
+    }
+    capture.entity = entity.id();
+    entity.observe(
+        move |event: On<ReadbackComplete>, mut frame: ResMut<CapturedFrame>| {
+            let Some(data) = unpad(&event.data, size) else {
+                return;
+            };
+            frame.data = data;
+            frame.size = size;
+            frame.count += 1;
+        },
+    );
+}
+
+// 読み出しを頼まれたフレームだけ Readback を付ける
+pub fn readback_system(mut cmds: Commands, mut capture: ResMut<Capture>) {
+    if capture.continuous {
+        return;
+    }
+    let requested = std::mem::take(&mut capture.requested);
+    if requested == capture.reading {
+        return;
+    }
+    if requested {
+        cmds.entity(capture.entity).insert(Readback::texture(capture.image.clone()));
+    } else {
+        cmds.entity(capture.entity).remove::<Readback>();
+    }
+    capture.reading = requested;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 画素ごとに [値, 値, 値, 255] の画像
+    fn frame(size: UVec2, values: &[u8]) -> CapturedFrame {
+        CapturedFrame {
+            size,
+            data: values.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            count: 1,
+        }
+    }
+
+    #[test]
+    fn unpads_rows() {
+        // 3 画素（12 バイト）の行は 256 バイトに揃えて読み出される
+        let size = UVec2::new(3, 2);
+        let mut data = vec![0xee; 256 + 12];
+        data[..12].copy_from_slice(&[1; 12]);
+        data[256..].copy_from_slice(&[2; 12]);
+        let rgba = unpad(&data, size).unwrap();
+        assert_eq!(rgba.len(), 24);
+        assert_eq!(&rgba[..12], &[1; 12]);
+        assert_eq!(&rgba[12..], &[2; 12]);
+
+        // 最後の行の詰め物はなくてもよいが、足りなければ None
+        assert!(unpad(&data[..256 + 11], size).is_none());
+
+        // 64 画素の行は詰め物なし
+        let data: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        assert_eq!(unpad(&data, UVec2::new(64, 2)).unwrap(), data);
+    }
+
+    #[test]
+    fn converts_to_rgb() {
+        assert_eq!(rgb(&[1, 2, 3, 255, 4, 5, 6, 0]), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn samples_averages() {
+        #[rustfmt::skip]
+        let frame = frame(UVec2::new(4, 2), &[
+            0, 100, 10, 20,
+            200, 100, 30, 40,
+        ]);
+        let screen = Vec2::new(4.0, 2.0);
+        let whole = Rect::from_corners(Vec2::ZERO, screen);
+        assert_eq!(frame.sample(screen, whole, 2, 1), [[100; 3], [25; 3]]);
+        assert_eq!(frame.sample(screen, whole, 1, 1), [[62; 3]]);
+        assert_eq!(frame.sample(screen, Rect::new(2.0, 1.0, 4.0, 2.0), 2, 1), [[30; 3], [40; 3]]);
+
+        // 画面より小さい解像度で読み出した場合も画面の座標で指定する
+        let screen = Vec2::new(8.0, 4.0);
+        assert_eq!(frame.sample(screen, Rect::new(0.0, 0.0, 4.0, 4.0), 1, 2), [[50; 3], [150; 3]]);
+    }
+
+    #[test]
+    fn samples_black_before_first_frame() {
+        let frame = CapturedFrame::default();
+        let screen = Vec2::new(4.0, 2.0);
+        assert_eq!(frame.sample(screen, Rect::from_corners(Vec2::ZERO, screen), 3, 2), [[0; 3]; 6]);
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use clap::{Parser, Subcommand};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::loader::Config;
+use crate::preset_file::PresetFormat;
+
+/// 電光掲示板
+///
+/// 各オプションは環境変数 (`EBB_*`) でも指定できる。優先順位はコマンドライン > 環境変数 > config.toml > 既定値。
+#[derive(Parser, Debug, Resource, Clone)]
+#[command(version, about)]
+pub struct Cli {
+    /// データディレクトリ（既定: ~/ebb）
+    #[arg(long, env = "EBB_DATA_DIR", value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// 設定ファイル（既定: <data-dir>/config.toml）
+    #[arg(long, env = "EBB_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// プリセットディレクトリ（既定: <data-dir>/presets）
+    #[arg(long, env = "EBB_PRESETS", value_name = "DIR")]
+    pub presets: Option<PathBuf>,
+
+    /// WebSocket サーバーの待ち受けアドレス（既定: 0.0.0.0:3000）
+    #[arg(long, env = "EBB_LISTEN", value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+
+    /// 起動時に表示するプリセット
+    #[arg(long, env = "EBB_PRESET", value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// 設定ファイルとプリセットを検査して終了（エラーがあれば終了コード 1）
+    #[arg(long)]
+    pub check: bool,
+
+    /// ウィンドウを開かずに画像に描画する（/snapshot.png で内容を確認できる）
+    #[arg(long, env = "EBB_HEADLESS")]
+    pub headless: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// プリセットファイルを別の形式（TOML/JSON/YAML）に変換
+    Convert {
+        /// 変換元のプリセットファイル
+        input: PathBuf,
+
+        /// 変換先の形式
+        #[arg(long, value_name = "FORMAT")]
+        to: PresetFormat,
+
+        /// 出力先（省略時は標準出力）
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// プリセットのエントリを指定した時刻まで進めて PNG に書き出す（ウィンドウは開かない）
+    Render {
+        /// プリセット名
+        preset: String,
+
+        /// エントリの番号（0 から）
+        #[arg(long, default_value_t = 0)]
+        index: usize,
+
+        /// エントリを表示してからの秒数
+        #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+        at: f32,
+
+        /// 出力先の PNG ファイル
+        #[arg(long, short, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// プリセットを最初から最後まで再生し、連番の PNG か RGB の生データ（標準出力）に書き出す（ウィンドウは開かない）
+    Export {
+        /// プリセット名
+        preset: String,
+
+        /// 連番の PNG（frame_00001.png, ...）を書き出すディレクトリ
+        #[arg(long, short, value_name = "DIR", required_unless_present = "raw")]
+        output: Option<PathBuf>,
+
+        /// PNG の代わりに RGB（1 画素 3 バイト）の生データを標準出力に書き出す（ffmpeg の rawvideo 用）
+        #[arg(long, conflicts_with = "output")]
+        raw: bool,
+
+        /// 1 秒あたりのフレーム数
+        #[arg(long, default_value_t = 30.0)]
+        fps: f32,
+
+        /// 画像の幅（既定: window_width、高さだけ指定した場合は縦横比に合わせる）
+        #[arg(long, value_name = "PX")]
+        width: Option<u32>,
+
+        /// 画像の高さ（既定: window_height、幅だけ指定した場合は縦横比に合わせる）
+        #[arg(long, value_name = "PX")]
+        height: Option<u32>,
+
+        /// 静止・ループするエントリを表示する秒数
+        #[arg(long, value_name = "SECONDS", default_value_t = 5.0)]
+        hold: f32,
+    },
+}
+
+impl Cli {
+    // render・export はウィンドウを開かずに描画する
+    pub fn headless(&self) -> bool {
+        self.headless || matches!(self.command, Some(Command::Render { .. } | Command::Export { .. }))
+    }
+
+    // export で指定された画像の大きさ（片方だけの場合は画面の縦横比に合わせる）
+    pub fn export_size(&self, screen: Vec2) -> Option<UVec2> {
+        let Some(Command::Export { width, height, .. }) = &self.command else {
+            return None;
+        };
+        let size = match (*width, *height) {
+            (None, None) => return None,
+            (Some(width), Some(height)) => UVec2::new(width, height),
+            (Some(width), None) => UVec2::new(width, (width as f32 * screen.y / screen.x).round() as u32),
+            (None, Some(height)) => UVec2::new((height as f32 * screen.x / screen.y).round() as u32, height),
+        };
+        Some(size.max(UVec2::ONE))
+    }
+
+    // コマンドライン・環境変数の値で config.toml の値を上書き
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(presets) = &self.presets {
+            // 相対パスはカレントディレクトリ基準で解決する
+            let presets = std::path::absolute(presets).unwrap_or_else(|_| presets.clone());
+            config.presets_dir = Some(presets);
+        }
+        if let Some(listen) = &self.listen {
+            config.listen = Some(listen.to_string());
+        }
+        if let Some(preset) = &self.preset {
+            config.default_preset = Some(preset.clone());
+        }
+    }
+}
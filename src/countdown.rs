@@ -3,6 +3,10 @@ use bevy::{
     prelude::*,
 };
 
+use crate::loader::TransitionKind;
+use crate::regions::{Region, RegionId, Regions};
+use crate::transition::Transition;
+
 #[derive(Resource, Default)]
 pub struct CountdownTimer {
     pub timer: Timer,
@@ -13,6 +17,7 @@ pub struct CountdownTimer {
     pub current_number_start_time: f32, // 現在の数字が表示開始された時間
     pub total_elapsed_time: f32, // カウントダウン開始からの総経過時間
     pub mode: CountdownMode, // カウントダウンモード
+    pub region: RegionId, // 数字を表示する領域
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -26,23 +31,6 @@ pub enum CountdownMode {
 #[derive(Component)]
 pub struct CountdownText;
 
-#[derive(Component)]
-pub struct FadeComponent {
-    pub fade_in_duration: f32,
-    pub fade_out_duration: f32,
-    pub display_duration: f32,
-    pub current_time: f32,
-    pub phase: FadePhase,
-}
-
-#[derive(Debug, PartialEq)]
-pub enum FadePhase {
-    FadeIn,
-    Display,
-    FadeOut,
-    Complete,
-}
-
 impl CountdownTimer {
     pub fn new(seconds: f32, mode: CountdownMode) -> Self {
         Self {
@@ -54,6 +42,7 @@ impl CountdownTimer {
             current_number_start_time: 0.0,
             total_elapsed_time: 0.0,
             mode,
+            region: RegionId::default(),
         }
     }
     
@@ -147,15 +136,13 @@ impl CountdownTimer {
     }
 }
 
-pub fn setup_countdown_timer(mut commands: Commands) {
-    commands.insert_resource(CountdownTimer::new(10.0, CountdownMode::Normal));
-}
-
 pub fn countdown_system(
     time: Res<Time>,
     mut countdown_timer: ResMut<CountdownTimer>,
     mut commands: Commands,
     fonts: Res<crate::Fonts>,
+    config: Res<crate::loader::Config>,
+    regions: Res<Regions>,
     text_query: Query<Entity, With<CountdownText>>,
 ) {
     if !countdown_timer.is_active {
@@ -186,7 +173,8 @@ pub fn countdown_system(
         let display_text = current_number.to_string();
         
         // 新しいカウントダウンテキストを表示（フェードイン/アウト付き）
-        spawn_countdown_text(&mut commands, &display_text, fonts.text_font.clone());
+        let region = regions.get(countdown_timer.region);
+        spawn_countdown_text(&mut commands, &display_text, fonts.text_font.clone(), &config, region);
         
         countdown_timer.last_displayed_number = current_number;
         countdown_timer.current_number_start_time = countdown_timer.total_elapsed_time;
@@ -208,89 +196,35 @@ pub fn countdown_system(
     }
 }
 
-pub fn fade_system(
-    time: Res<Time>,
-    mut query: Query<(Entity, &mut FadeComponent, &mut TextColor), With<CountdownText>>,
-    mut commands: Commands,
-) {
-    let mut entities_to_remove = Vec::new();
-    
-    for (entity, mut fade, mut text_color) in query.iter_mut() {
-        fade.current_time += time.delta_secs();
-        
-        let alpha = match fade.phase {
-            FadePhase::FadeIn => {
-                if fade.current_time >= fade.fade_in_duration {
-                    fade.phase = FadePhase::Display;
-                    fade.current_time = 0.0;
-                    1.0
-                } else {
-                    fade.current_time / fade.fade_in_duration
-                }
-            }
-            FadePhase::Display => {
-                if fade.current_time >= fade.display_duration {
-                    fade.phase = FadePhase::FadeOut;
-                    fade.current_time = 0.0;
-                }
-                1.0
-            }
-            FadePhase::FadeOut => {
-                if fade.current_time >= fade.fade_out_duration {
-                    fade.phase = FadePhase::Complete;
-                    0.0
-                } else {
-                    1.0 - (fade.current_time / fade.fade_out_duration)
-                }
-            }
-            FadePhase::Complete => 0.0,
-        };
-        
-        // アルファ値を適用
-        let mut color = text_color.0;
-        color.set_alpha(alpha.clamp(0.0, 1.0));
-        text_color.0 = color;
-        
-        // フェードアウト完了時にエンティティを削除リストに追加
-        if fade.phase == FadePhase::Complete {
-            entities_to_remove.push(entity);
-        }
-    }
-    
-    // 遅延削除でエンティティを安全に削除
-    for entity in entities_to_remove {
-        match commands.get_entity(entity) {
-            Ok(mut entity_commands) => {
-                entity_commands.despawn();
-            }
-            Err(_) => {
-                // エンティティが既に削除されている場合は無視
-            }
-        }
-    }
-}
-
 fn spawn_countdown_text(
     commands: &mut Commands,
     text: &str,
-    text_font: TextFont,
+    mut text_font: TextFont,
+    config: &crate::loader::Config,
+    region: &Region,
 ) {
-    commands.spawn((
+    if let Some(text_size) = region.text_size {
+        text_font.font_size = text_size;
+    }
+    let fit_mode = config.auto_fit.mode;
+    let transform = crate::transition::hidden_until_started(Transform::from_xyz(0.0, 0.0, 0.0), TransitionKind::Fade);
+    let mut entity = commands.spawn((
         Text2d::new(text),
         text_font,
-        TextColor(Color::srgba(1.0, 1.0, 0.3, 0.0)), // 初期は透明
-        Transform::from_xyz(0.0, 0.0, 0.0),
+        TextColor(Color::srgb(1.0, 1.0, 0.3)),
+        crate::fit::hidden_until_fitted(transform, fit_mode),
         TextLayout::default(),
         CountdownText,
-        FadeComponent {
-            fade_in_duration: 0.3,
-            fade_out_duration: 0.3,
-            display_duration: 0.4,
-            current_time: 0.0,
-            phase: FadePhase::FadeIn,
-        },
+        region.id,
+        region.id.layer(),
+        // 0.3秒でフェードイン、0.4秒表示、0.3秒でフェードアウトして削除
+        Transition::new(TransitionKind::Fade, 0.3, Vec2::ZERO).with_hold(0.4),
         crate::Showing,
     ));
+    // 数字を表示領域に合わせる（設定ファイルの auto_fit）
+    if fit_mode != crate::loader::AutoFit::Off {
+        entity.insert(crate::fit::Fitting::new(fit_mode, false, 1.0));
+    }
 }
 
 pub fn countdown_finished_system(
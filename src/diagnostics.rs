@@ -0,0 +1,180 @@
+use bevy::{
+    color::palettes::{css::WHITE, tailwind::RED_700},
+    prelude::*,
+};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// 設定ファイル・プリセットの問題一つ分
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub line: Option<usize>,   // 1始まり
+    pub column: Option<usize>, // 1始まり
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+// 一つのファイル群（設定ファイル、またはプリセット）についての問題の一覧
+#[derive(Debug, Default, Clone)]
+pub struct Report {
+    pub items: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn error(&mut self, file: &Path, line: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Error, file, line, None, message.into());
+    }
+
+    pub fn warning(&mut self, file: &Path, line: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Warning, file, line, None, message.into());
+    }
+
+    // バイト位置から行・列を求めて追加
+    pub fn error_at(&mut self, file: &Path, source: &str, offset: usize, message: impl Into<String>) {
+        let (line, column) = line_column(source, offset);
+        self.push(Severity::Error, file, Some(line), Some(column), message.into());
+    }
+
+    pub fn warning_at(&mut self, file: &Path, source: &str, offset: usize, message: impl Into<String>) {
+        let (line, column) = line_column(source, offset);
+        self.push(Severity::Warning, file, Some(line), Some(column), message.into());
+    }
+
+    pub fn error_at_position(&mut self, file: &Path, line: usize, column: usize, message: impl Into<String>) {
+        self.push(Severity::Error, file, Some(line), Some(column), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, file: &Path, line: Option<usize>, column: Option<usize>, message: String) {
+        self.items.push(Diagnostic {
+            severity,
+            file: file.to_path_buf(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source.as_bytes()[..offset];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+    (line, column)
+}
+
+// 起動時・再読み込み時の診断結果
+#[derive(Resource, Debug, Default)]
+pub struct Diagnostics {
+    pub config: Report,
+    pub presets: Report,
+    pub dismissed: bool,
+}
+
+impl Diagnostics {
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.config.items.iter().chain(self.presets.items.iter())
+    }
+
+    // エラーがある場合は既定値・デモテキストで動作している（縮退状態）
+    pub fn has_errors(&self) -> bool {
+        self.config.has_errors() || self.presets.has_errors()
+    }
+
+    pub fn print(&self) {
+        for diagnostic in self.iter() {
+            eprintln!("{}", diagnostic);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ErrorBanner;
+
+// 診断結果が変わったらエラーバナーを作り直す（Esc で非表示）
+pub fn error_banner_system(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut diagnostics: ResMut<Diagnostics>,
+    fonts: Res<crate::Fonts>,
+    banner_query: Query<Entity, With<ErrorBanner>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) && !banner_query.is_empty() {
+        diagnostics.dismissed = true;
+    }
+    if !diagnostics.is_changed() {
+        return;
+    }
+
+    for entity in banner_query.iter() {
+        cmds.entity(entity).despawn();
+    }
+    if diagnostics.dismissed || !diagnostics.has_errors() {
+        return;
+    }
+
+    let errors: Vec<String> = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.to_string())
+        .collect();
+    let mut message = format!("{} error(s) in config/presets (Esc to dismiss)", errors.len());
+    for line in errors.iter().take(5) {
+        message.push('\n');
+        message.push_str(line);
+    }
+    if errors.len() > 5 {
+        message.push_str(&format!("\n... and {} more", errors.len() - 5));
+    }
+
+    cmds.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(0.0),
+            left: Val::Px(0.0),
+            right: Val::Px(0.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::Srgba(RED_700)),
+        ErrorBanner,
+    ))
+    .with_child((
+        Text::new(message),
+        TextFont {
+            font: fonts.text_font.font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::Srgba(WHITE)),
+    ));
+}
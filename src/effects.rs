@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+use crate::fonts::{self, FontFallbackPending};
+use crate::fit::Fitting;
+use crate::loader::AutoFit;
+use crate::text_spawner::{render_runs, StyledText};
+use crate::transition::Transition;
+
+// プリセットの effect 列の一項目
+//
+// "pulse" のように名前だけ、または "pulse:period=2,min=0.2" のように引数を付けて書く。
+// "rainbow+shake" のように + でつなぐと複数の効果を重ねられる。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    Blink { period: f32 },                  // period 秒ごとに表示・非表示を切り替え
+    Pulse { period: f32, min: f32 },        // 透明度を min〜1 の間で周期的に変える
+    Rainbow { period: f32 },                // period 秒で色相を一周させる
+    Shake { amplitude: f32, frequency: f32 }, // 1秒に frequency 回、最大 amplitude px ずらす
+    Typewriter { speed: f32 },              // 1秒に speed 文字ずつ表示する
+}
+
+// effect 列を解釈する（空欄・none は効果なし）
+pub fn parse(effect: &str) -> Result<Vec<Effect>, String> {
+    let mut effects = Vec::new();
+    for item in effect.split('+').map(str::trim) {
+        if item.is_empty() || item == "none" {
+            continue;
+        }
+        let (name, args) = item.split_once(':').unwrap_or((item, ""));
+        let mut params = HashMap::new();
+        for arg in args.split(',').map(str::trim).filter(|arg| !arg.is_empty()) {
+            let Some((key, value)) = arg.split_once('=') else {
+                return Err(format!("missing value for `{}` in effect `{}`", arg, item));
+            };
+            let value: f32 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid number `{}` in effect `{}`", value.trim(), item))?;
+            if value.is_nan() || value < 0.0 {
+                return Err(format!("`{}` must not be negative in effect `{}`", key.trim(), item));
+            }
+            params.insert(key.trim(), value);
+        }
+
+        let name = name.trim();
+        let keys: &[&str] = match name {
+            "blink" | "rainbow" => &["period"],
+            "pulse" => &["period", "min"],
+            "shake" => &["amplitude", "frequency"],
+            "typewriter" => &["speed"],
+            _ => return Err(format!("unknown effect `{}`", name)),
+        };
+        if let Some(key) = params.keys().find(|key| !keys.contains(key)) {
+            return Err(format!("unknown parameter `{}` for effect `{}`", key, name));
+        }
+        // 周期・速さは 0 にできない
+        let positive = |key: &str, default: f32| match params.get(key) {
+            Some(&value) if value <= 0.0 => Err(format!("`{}` must be positive in effect `{}`", key, name)),
+            Some(&value) => Ok(value),
+            None => Ok(default),
+        };
+        effects.push(match name {
+            "blink" => Effect::Blink {
+                period: positive("period", 0.5)?,
+            },
+            "pulse" => Effect::Pulse {
+                period: positive("period", 1.0)?,
+                min: params.get("min").copied().unwrap_or(0.3).min(1.0),
+            },
+            "rainbow" => Effect::Rainbow {
+                period: positive("period", 3.0)?,
+            },
+            "shake" => Effect::Shake {
+                amplitude: params.get("amplitude").copied().unwrap_or(6.0),
+                frequency: positive("frequency", 30.0)?,
+            },
+            _ => Effect::Typewriter {
+                speed: positive("speed", 12.0)?,
+            },
+        });
+    }
+    Ok(effects)
+}
+
+// 効果のコンポーネント
+#[derive(Component)]
+pub struct Blink {
+    pub period: f32,
+    pub elapsed: f32,
+}
+
+#[derive(Component)]
+pub struct Pulse {
+    pub period: f32,
+    pub min: f32,
+    pub elapsed: f32,
+    base_alpha: HashMap<Entity, (f32, f32)>, // 文字・背景の元の透明度
+}
+
+#[derive(Component)]
+pub struct Rainbow {
+    pub period: f32,
+    pub elapsed: f32,
+    base_color: HashMap<Entity, Color>,
+}
+
+#[derive(Component)]
+pub struct Shake {
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub elapsed: f32,
+    applied_offset: Vec2,
+}
+
+// 表示する文字数は StyledText の style.reveal に持つ（作り直しても変わらないように）
+#[derive(Component)]
+pub struct Typewriter {
+    pub speed: f32,
+    pub elapsed: f32,
+}
+
+// タイプライターの場合、最初は一文字も表示しない
+pub fn initial_reveal(effects: &[Effect]) -> Option<usize> {
+    effects
+        .iter()
+        .any(|effect| matches!(effect, Effect::Typewriter { .. }))
+        .then_some(0)
+}
+
+// エントリの効果に応じたコンポーネントを追加
+pub fn insert_effects(entity: &mut EntityCommands, effects: &[Effect]) {
+    for effect in effects {
+        match *effect {
+            Effect::Blink { period } => {
+                entity.insert(Blink { period, elapsed: 0.0 });
+            }
+            Effect::Pulse { period, min } => {
+                entity.insert(Pulse {
+                    period,
+                    min,
+                    elapsed: 0.0,
+                    base_alpha: HashMap::new(),
+                });
+            }
+            Effect::Rainbow { period } => {
+                entity.insert(Rainbow {
+                    period,
+                    elapsed: 0.0,
+                    base_color: HashMap::new(),
+                });
+            }
+            Effect::Shake { amplitude, frequency } => {
+                entity.insert(Shake {
+                    amplitude,
+                    frequency,
+                    elapsed: 0.0,
+                    applied_offset: Vec2::ZERO,
+                });
+            }
+            Effect::Typewriter { speed } => {
+                entity.insert(Typewriter { speed, elapsed: 0.0 });
+            }
+        }
+    }
+}
+
+pub fn blink_system(
+    time: Res<Time>,
+    mut query: Query<(&mut Blink, &mut Visibility)>,
+) {
+    for (mut blink, mut visibility) in &mut query {
+        blink.elapsed += time.delta_secs();
+        let visible = ((blink.elapsed / blink.period) as u32).is_multiple_of(2);
+        *visibility = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+// 親の Text2d と子の TextSpan
+fn text_entities(entity: Entity, children: Option<&Children>) -> impl Iterator<Item = Entity> + '_ {
+    std::iter::once(entity).chain(children.into_iter().flat_map(|children| children.iter()))
+}
+
+pub fn pulse_system(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Pulse, Option<&Children>, Option<&Transition>)>,
+    mut colors: Query<(&mut TextColor, Option<&mut TextBackgroundColor>)>,
+) {
+    for (entity, mut pulse, children, transition) in &mut query {
+        // フェードで現れる・消える間はそちらに任せる
+        if transition.is_some_and(Transition::is_fading) {
+            continue;
+        }
+        pulse.elapsed += time.delta_secs();
+        let wave = (1.0 + (pulse.elapsed / pulse.period * std::f32::consts::TAU).cos()) / 2.0;
+        let factor = pulse.min + (1.0 - pulse.min) * wave;
+        // 作り直しで消えた部分の元の透明度は捨てる
+        let targets: Vec<Entity> = text_entities(entity, children).collect();
+        pulse.base_alpha.retain(|target, _| targets.contains(target));
+        for &target in &targets {
+            let Ok((mut text_color, mut background)) = colors.get_mut(target) else {
+                continue;
+            };
+            let base = *pulse.base_alpha.entry(target).or_insert_with(|| {
+                (text_color.0.alpha(), background.as_ref().map_or(0.0, |b| b.0.alpha()))
+            });
+            text_color.0.set_alpha(base.0 * factor);
+            if let Some(background) = &mut background {
+                background.0.set_alpha(base.1 * factor);
+            }
+        }
+    }
+}
+
+pub fn rainbow_system(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Rainbow, Option<&Children>)>,
+    mut colors: Query<&mut TextColor>,
+) {
+    for (entity, mut rainbow, children) in &mut query {
+        rainbow.elapsed += time.delta_secs();
+        let degrees = rainbow.elapsed / rainbow.period * 360.0;
+        let targets: Vec<Entity> = text_entities(entity, children).collect();
+        rainbow.base_color.retain(|target, _| targets.contains(target));
+        // 部分ごとに少しずつ色相をずらす
+        for (i, &target) in targets.iter().enumerate() {
+            let Ok(mut text_color) = colors.get_mut(target) else {
+                continue;
+            };
+            let base = *rainbow.base_color.entry(target).or_insert(text_color.0);
+            // 白・灰色などの無彩色でも色が付くよう、彩度を上げる
+            let mut color = Hsla::from(base);
+            color.saturation = color.saturation.max(0.8);
+            color.lightness = color.lightness.clamp(0.4, 0.7);
+            let color = color.rotate_hue(degrees + i as f32 * 30.0).with_alpha(text_color.0.alpha());
+            text_color.0 = color.into();
+        }
+    }
+}
+
+pub fn shake_system(
+    time: Res<Time>,
+    mut query: Query<(&mut Shake, &mut Transform), Without<crate::text_spawner::PendingLayout>>,
+) {
+    let mut rng = rand::rng();
+    for (mut shake, mut transform) in &mut query {
+        let previous = (shake.elapsed * shake.frequency) as u32;
+        shake.elapsed += time.delta_secs();
+        if (shake.elapsed * shake.frequency) as u32 == previous {
+            continue;
+        }
+        // スクロールと両立させるため、前回ずらした分との差だけ動かす
+        let angle = rng.random_range(0.0..std::f32::consts::TAU);
+        let offset = Vec2::from_angle(angle) * shake.amplitude * rng.random_range(0.0..=1.0f32);
+        transform.translation += (offset - shake.applied_offset).extend(0.0);
+        shake.applied_offset = offset;
+    }
+}
+
+// 表示する文字数が変わったら作り直す（大きさは変えない）
+#[allow(clippy::type_complexity)]
+pub fn typewriter_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    font_assets: Res<Assets<Font>>,
+    mut query: Query<(Entity, &mut Typewriter, &mut StyledText), (Without<FontFallbackPending>, Without<Fitting>)>,
+) {
+    for (entity, mut typewriter, mut styled_text) in &mut query {
+        typewriter.elapsed += time.delta_secs();
+        let total: usize = styled_text.spans.iter().map(|span| span.text.chars().count()).sum();
+        let reveal = ((typewriter.elapsed * typewriter.speed) as usize).min(total);
+        if styled_text.style.reveal == Some(reveal) {
+            continue;
+        }
+        styled_text.style.reveal = Some(reveal);
+
+        let chains: Vec<_> = styled_text.spans.iter().map(|span| styled_text.style.font_chain(span)).collect();
+        let runs = fonts::split_runs(&styled_text.spans, &chains, &font_assets);
+        let style = crate::text_spawner::EntryStyle {
+            fit: AutoFit::Off,
+            ..styled_text.style.clone()
+        };
+        render_runs(&mut cmds.entity(entity), &runs, &style);
+        if reveal == total {
+            styled_text.style.reveal = None;
+            cmds.entity(entity).remove::<Typewriter>();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE};
+use std::error::Error;
+use std::path::Path;
+
+// 文字コードを判定してファイルを読み込む
+//
+// BOM 付きの UTF-8 / UTF-16、BOM なしの UTF-8 / UTF-16、Shift_JIS (CP932) に対応。
+// Excel の「CSV (コンマ区切り)」は Shift_JIS、「CSV UTF-8」は BOM 付き UTF-8、
+// 「Unicode テキスト」は BOM 付き UTF-16LE（タブ区切り）で保存される。
+pub fn read_text(path: &Path) -> Result<(String, &'static str), Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    decode(&bytes)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<(String, &'static str), Box<dyn Error>> {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        if had_errors {
+            return Err(format!("invalid {} text", encoding.name()).into());
+        }
+        let name = match encoding.name() {
+            "UTF-8" => "UTF-8 (BOM)",
+            name => name,
+        };
+        return Ok((text.into_owned(), name));
+    }
+
+    // NUL を含む UTF-16 も UTF-8 としては正しいため先に判定する
+    if let Some(encoding) = guess_utf16(bytes) {
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if !had_errors {
+            return Ok((text.into_owned(), encoding.name()));
+        }
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok((text.to_string(), "UTF-8"));
+    }
+
+    // encoding_rs の Shift_JIS は CP932 (Windows-31J) の拡張文字も含む
+    let (text, had_errors) = SHIFT_JIS.decode_without_bom_handling(bytes);
+    if !had_errors {
+        return Ok((text.into_owned(), "Shift_JIS"));
+    }
+
+    Err("unknown text encoding (expected UTF-8, UTF-16 or Shift_JIS)".into())
+}
+
+// BOM なし UTF-16: ASCII 文字の上位バイト (0x00) が偶数・奇数どちらの位置に多いかで判定
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    let half = bytes.len() / 2;
+    if odd_zeros * 2 > half && even_zeros * 8 < half {
+        Some(UTF_16LE)
+    } else if even_zeros * 2 > half && odd_zeros * 8 < half {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+// ヘッダー行にカンマがなくタブがある場合はタブ区切りとみなす
+pub fn detect_delimiter(text: &str) -> u8 {
+    let header = text.lines().next().unwrap_or_default();
+    if !header.contains(',') && header.contains('\t') {
+        b'\t'
+    } else {
+        b','
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn decodes_utf8_with_and_without_bom() {
+        let text = "content,duration\nお知らせ,5\n";
+        assert_eq!(decode(text.as_bytes()).unwrap(), (text.to_string(), "UTF-8"));
+
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(text.as_bytes());
+        assert_eq!(decode(&bytes).unwrap(), (text.to_string(), "UTF-8 (BOM)"));
+    }
+
+    #[test]
+    fn decodes_utf16_with_bom() {
+        let text = "content\tduration\nお知らせ\t5\n";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16le(text));
+        assert_eq!(decode(&bytes).unwrap(), (text.to_string(), "UTF-16LE"));
+
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(utf16be(text));
+        assert_eq!(decode(&bytes).unwrap(), (text.to_string(), "UTF-16BE"));
+    }
+
+    #[test]
+    fn guesses_utf16_without_bom() {
+        let text = "content,duration\nHello,5\n";
+        assert_eq!(guess_utf16(&utf16le(text)), Some(UTF_16LE));
+        assert_eq!(guess_utf16(&utf16be(text)), Some(UTF_16BE));
+        assert_eq!(decode(&utf16le(text)).unwrap(), (text.to_string(), "UTF-16LE"));
+        assert_eq!(decode(&utf16be(text)).unwrap(), (text.to_string(), "UTF-16BE"));
+
+        // ASCII だけの UTF-8・奇数長は UTF-16 とみなさない
+        assert_eq!(guess_utf16(b"content,duration\n"), None);
+        assert_eq!(guess_utf16(&utf16le(text)[1..]), None);
+    }
+
+    #[test]
+    fn decodes_shift_jis() {
+        let text = "content,duration\n本日は晴天なり①,5\n";
+        let (bytes, _, _) = SHIFT_JIS.encode(text);
+        assert!(std::str::from_utf8(&bytes).is_err());
+        assert_eq!(decode(&bytes).unwrap(), (text.to_string(), "Shift_JIS"));
+    }
+
+    #[test]
+    fn rejects_unknown_encoding() {
+        assert!(decode(&[0x82, 0xFF, 0xFF, 0x82, 0xA0]).is_err());
+    }
+
+    #[test]
+    fn detects_tab_delimiter() {
+        assert_eq!(detect_delimiter("content\tduration\na,b\t5"), b'\t');
+        assert_eq!(detect_delimiter("content,duration\ta"), b',');
+    }
+}
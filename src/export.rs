@@ -0,0 +1,203 @@
+use bevy::{prelude::*, render::gpu_readback::ReadbackComplete};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::bingo::BingoState;
+use crate::capture::{self, Capture};
+use crate::loader::{Config, DataPaths};
+use crate::regions::{self, RegionId, Regions};
+use crate::render;
+use crate::snapshot;
+use crate::template::Variables;
+use crate::text_spawner;
+use crate::transition::{self, Leaving, ShowingQuery};
+use crate::Fonts;
+
+// 最後のエントリが終わってから、残りのフレームの読み出しを待つフレーム数
+const READBACK_WAIT_FRAMES: u32 = 120;
+
+// 書き出し先
+pub enum ExportOutput {
+    Frames(PathBuf), // 連番の PNG を書き出すディレクトリ
+    Raw(File),       // RGB の生データ（標準出力）
+}
+
+// export サブコマンドの作業（最初の領域でプリセットを最後まで再生し、毎フレーム書き出す）
+//
+// 時間は 1 フレームごとに 1/fps 秒ずつ進め、描画したフレームは読み出した順にすべて書き出す。
+#[derive(Resource)]
+pub struct ExportJob {
+    preset: String,
+    fps: f32,
+    hold: f64,
+    output: ExportOutput,
+    state: ExportState,
+    hold_until: Option<f64>, // 静止・ループするエントリを消す時刻
+    rendered: u64,           // 読み出しを頼んだフレーム数
+    written: u64,
+    waited: u32, // 終わってから待ったフレーム数
+    error: Option<String>,
+}
+
+enum ExportState {
+    Loading,   // フォントの読み込み待ち
+    Recording, // 再生しながら毎フレーム読み出す
+    Finished,  // 最後のエントリが終わり、残りのフレームの読み出しを待つ
+}
+
+impl ExportJob {
+    pub fn new(preset: &str, fps: f32, hold: f32, output: ExportOutput) -> Self {
+        Self {
+            preset: preset.to_string(),
+            fps,
+            hold: hold as f64,
+            output,
+            state: ExportState::Loading,
+            hold_until: None,
+            rendered: 0,
+            written: 0,
+            waited: 0,
+            error: None,
+        }
+    }
+
+    fn write(&mut self, size: UVec2, rgba: &[u8]) -> Result<(), String> {
+        match &mut self.output {
+            ExportOutput::Frames(dir) => {
+                let path = dir.join(format!("frame_{:05}.png", self.written + 1));
+                let png = snapshot::encode_png(size, rgba).map_err(|e| e.to_string())?;
+                std::fs::write(&path, png).map_err(|e| format!("{:?}: {}", path, e))
+            }
+            ExportOutput::Raw(file) => file.write_all(&capture::rgb(rgba)).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn destination(&self) -> String {
+        match &self.output {
+            ExportOutput::Frames(dir) => format!("{:?}", dir),
+            ExportOutput::Raw(_) => "stdout".to_string(),
+        }
+    }
+}
+
+// 標準出力を書き出し専用にし、ログ（println!）は標準エラー出力に出るようにする
+#[cfg(unix)]
+pub fn take_stdout() -> std::io::Result<File> {
+    use std::os::fd::FromRawFd;
+    std::io::stdout().flush()?;
+    // SAFETY: 標準出力・標準エラー出力の番号を複製するだけで、複製した番号は File が持つ
+    unsafe {
+        let fd = libc::dup(libc::STDOUT_FILENO);
+        if fd < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(File::from_raw_fd(fd))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn take_stdout() -> std::io::Result<File> {
+    Err(std::io::Error::other("--raw is not supported on this platform"))
+}
+
+// エントリを順に表示し、表示している間は毎フレーム読み出す
+#[allow(clippy::too_many_arguments)]
+pub fn export_system(
+    mut cmds: Commands,
+    mut job: ResMut<ExportJob>,
+    time: Res<Time>,
+    mut regions: ResMut<Regions>,
+    fonts: Res<Fonts>,
+    config: Res<Config>,
+    paths: Res<DataPaths>,
+    asset_server: Res<AssetServer>,
+    bingo: Res<BingoState>,
+    variables: Res<Variables>,
+    mut capture: ResMut<Capture>,
+    text_query: ShowingQuery,
+    leaving: Query<Option<&RegionId>, With<Leaving>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if let Some(e) = &job.error {
+        println!("Err: Can't write frame: {}", e);
+        exit.write(AppExit::error());
+        return;
+    }
+    match job.state {
+        ExportState::Loading => {
+            if !render::fonts_loaded(&fonts, &asset_server) {
+                return;
+            }
+            // 読み出しの結果は 1 フレームに複数届くことがあるので、届くたびに書き出す
+            let size = capture.size;
+            cmds.entity(capture.entity()).observe(
+                move |event: On<ReadbackComplete>, mut job: ResMut<ExportJob>| {
+                    if job.written >= job.rendered || job.error.is_some() {
+                        return;
+                    }
+                    let Some(rgba) = capture::unpad(&event.data, size) else {
+                        return;
+                    };
+                    match job.write(size, &rgba) {
+                        Ok(()) => job.written += 1,
+                        Err(e) => job.error = Some(e),
+                    }
+                },
+            );
+            job.state = ExportState::Recording;
+        }
+        ExportState::Recording => {}
+        ExportState::Finished => {
+            if job.written >= job.rendered {
+                println!(
+                    "Exported {} frames of {} ({}x{}, {} fps) to {}",
+                    job.written,
+                    job.preset,
+                    capture.size.x,
+                    capture.size.y,
+                    job.fps,
+                    job.destination()
+                );
+                exit.write(AppExit::Success);
+            } else if job.waited >= READBACK_WAIT_FRAMES {
+                println!(
+                    "Err: {} of {} frames were not read back, {} is incomplete",
+                    job.rendered - job.written,
+                    job.rendered,
+                    job.destination()
+                );
+                exit.write(AppExit::error());
+            } else {
+                job.waited += 1;
+            }
+            return;
+        }
+    }
+
+    let region = regions.get_mut(RegionId(0));
+    let showing = text_query.iter().any(|(_, _, id)| id.copied().unwrap_or_default() == region.id);
+    let elapsed = time.elapsed_secs_f64();
+    if showing {
+        // 自分では終わらないエントリ（静止・ループ）は hold 秒で次に進む
+        if let Some(until) = job.hold_until
+            && elapsed >= until
+        {
+            transition::dismiss_all(&mut cmds, &text_query, region.id);
+            job.hold_until = None;
+        }
+    } else if region.queue.current_index < region.queue.texts.len() {
+        let index = region.queue.current_index;
+        let entry = regions::spawn_entry(&mut cmds, region, index, &fonts, &config, &paths, &asset_server, &bingo, &variables);
+        region.queue.current_index += 1;
+        region.scrolling.is_active = true;
+        let held = entry.is_some_and(|(entry, shown)| shown && text_spawner::never_ends(entry.duration));
+        job.hold_until = held.then_some(elapsed + job.hold);
+    } else if !leaving.iter().any(|id| id.copied().unwrap_or_default() == region.id) {
+        // 最後のエントリが消えたら終わり
+        job.state = ExportState::Finished;
+        return;
+    }
+    capture.requested = true;
+    job.rendered += 1;
+}
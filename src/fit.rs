@@ -0,0 +1,101 @@
+use bevy::{prelude::*, text::TextLayoutInfo};
+
+use crate::fonts::FontFallbackPending;
+use crate::loader::{AutoFit, Config};
+use crate::regions::{RegionId, Regions};
+use crate::text_spawner::StyledText;
+
+// 大きさの調整を繰り返す最大回数（折り返しありでは行数が変わるため一回では決まらない）
+const MAX_ITERATIONS: u32 = 8;
+
+// 表示領域に収まるよう文字の大きさを調整中の静止テキスト
+#[derive(Component)]
+pub struct Fitting {
+    pub mode: AutoFit,
+    pub wrap: bool,
+    pub scale: f32, // 元の文字サイズに対する倍率
+    pub iterations: u32,
+}
+
+impl Fitting {
+    pub fn new(mode: AutoFit, wrap: bool, scale: f32) -> Self {
+        Self {
+            mode,
+            wrap,
+            scale,
+            iterations: 0,
+        }
+    }
+}
+
+// 初めて表示するテキストは、大きさが決まるまで縮小（Transform の scale = 0）して隠しておく
+pub fn hidden_until_fitted(transform: Transform, mode: AutoFit) -> Transform {
+    match mode {
+        AutoFit::Off => transform,
+        AutoFit::Shrink | AutoFit::Fit => transform.with_scale(Vec3::ZERO),
+    }
+}
+
+// レイアウト後の大きさを表示領域と比べ、文字サイズを変えてレイアウトし直す
+#[allow(clippy::type_complexity)]
+pub fn auto_fit_system(
+    mut cmds: Commands,
+    config: Res<Config>,
+    regions: Res<Regions>,
+    mut query: Query<
+        (Entity, &mut Fitting, &TextLayoutInfo, &mut Transform, Option<&mut StyledText>, Option<&Children>, Option<&RegionId>),
+        Without<FontFallbackPending>,
+    >,
+    mut text_fonts: Query<&mut TextFont>,
+) {
+    for (entity, mut fitting, info, mut transform, styled_text, children, region) in &mut query {
+        let safe_area = regions.get(region.copied().unwrap_or_default()).safe_area(&config);
+        if info.size.x <= 0.0 || info.size.y <= 0.0 {
+            continue;
+        }
+        let fit_width = safe_area.x / info.size.x;
+        let fit_height = safe_area.y / info.size.y;
+        let mut factor = fit_width.min(fit_height);
+        // 折り返しありで高さが足りない場合、面積（行数 × 幅）で効くため平方根で調整する
+        if fitting.wrap && fit_height < fit_width {
+            factor = factor.sqrt();
+        }
+
+        // 表示領域の 95〜100% に収まれば終了
+        let done = match fitting.mode {
+            AutoFit::Off => true,
+            AutoFit::Shrink => factor >= 1.0 && (factor <= 1.05 || fitting.scale >= 1.0),
+            AutoFit::Fit => (1.0..=1.05).contains(&factor),
+        };
+        if done {
+            transform.scale = Vec3::ONE;
+            cmds.entity(entity).remove::<Fitting>();
+            continue;
+        }
+
+        // 少し小さめ（98%）を狙う
+        let mut scale = fitting.scale * factor * 0.98;
+        if fitting.mode == AutoFit::Shrink {
+            scale = scale.min(1.0);
+        }
+        let ratio = scale / fitting.scale;
+        let targets = std::iter::once(entity).chain(children.into_iter().flat_map(|children| children.iter()));
+        for target in targets {
+            if let Ok(mut text_font) = text_fonts.get_mut(target) {
+                text_font.font_size *= ratio;
+            }
+        }
+        fitting.scale = scale;
+        fitting.iterations += 1;
+        // 作り直すときは調整後の大きさから始める
+        if let Some(mut styled_text) = styled_text {
+            styled_text.style.fit_scale = scale;
+        }
+
+        // 決まらない場合は最後の大きさで表示する
+        if fitting.iterations >= MAX_ITERATIONS {
+            transform.scale = Vec3::ONE;
+            cmds.entity(entity).remove::<Fitting>();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::{
+    asset::{io::AssetSourceBuilder, LoadState},
+    prelude::*,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::loader::{Config, DataPaths};
+use crate::markup::Span;
+use crate::text_spawner::{render_runs, StyledText};
+
+// データディレクトリ（~/ebb）のアセットソース名（data://fonts/xxx.ttf）
+const DATA_SOURCE: &str = "data";
+
+// 同梱の assets に入っている既定のフォント
+const DEFAULT_FONT: &str = "fonts/ipag.ttf";
+
+// DefaultPlugins より前に呼ぶ
+pub fn register_data_source(app: &mut App, paths: &DataPaths) {
+    let data_dir = paths.data_dir.to_string_lossy().into_owned();
+    app.register_asset_source(DATA_SOURCE, AssetSourceBuilder::platform_default(&data_dir, None));
+}
+
+#[derive(Resource, Default)]
+pub struct Fonts {
+    pub text_font: TextFont,
+    pub bold_font: Handle<Font>,
+    pub fallback: Vec<Handle<Font>>, // 文字がない場合に順に試すフォント
+    pub named: BTreeMap<String, Handle<Font>>, // エントリの font 列で名前で選べるフォント
+    data_dir: PathBuf,
+}
+
+impl Fonts {
+    pub fn load(config: &Config, paths: &DataPaths, asset_server: &AssetServer) -> Self {
+        let mut fonts = Self {
+            data_dir: paths.data_dir.clone(),
+            ..default()
+        };
+        let primary = config.fonts.primary.as_deref().unwrap_or(DEFAULT_FONT);
+        fonts.named = config
+            .fonts
+            .named
+            .iter()
+            .map(|(name, path)| (name.clone(), fonts.load_path(path, asset_server)))
+            .collect();
+        fonts.text_font = TextFont {
+            font: fonts.font(primary, asset_server),
+            font_size: config.text_size,
+            ..default()
+        };
+        // bold_font が未指定の場合は通常のフォントで代用する
+        fonts.bold_font = match config.bold_font.as_deref() {
+            Some(bold_font) => fonts.font(bold_font, asset_server),
+            None => fonts.text_font.font.clone(),
+        };
+        fonts.fallback = config.fonts.fallback.iter().map(|font| fonts.font(font, asset_server)).collect();
+        fonts
+    }
+
+    // [fonts.named] の名前、またはフォントファイルのパス
+    pub fn font(&self, name: &str, asset_server: &AssetServer) -> Handle<Font> {
+        match self.named.get(name) {
+            Some(font) => font.clone(),
+            None => self.load_path(name, asset_server),
+        }
+    }
+
+    fn load_path(&self, path: &str, asset_server: &AssetServer) -> Handle<Font> {
+        asset_server.load(asset_path(&self.data_dir, path))
+    }
+}
+
+// データディレクトリにあればそちらを（data://...）、なければ同梱の assets のパスを返す
+pub fn asset_path(data_dir: &Path, path: &str) -> String {
+    let relative = Path::new(path).strip_prefix(data_dir).unwrap_or(Path::new(path));
+    if data_dir.join(relative).is_file() {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        format!("{}://{}", DATA_SOURCE, relative)
+    } else {
+        path.to_string()
+    }
+}
+
+// 文字ごとに、エントリのフォント → 代替フォントの順で字形のあるものを選ぶ
+//
+// 同じフォントが続く部分は一つにまとめ、(部分, フォント) の一覧を返す。
+// どのフォントにも字形がない文字は元のフォントのまま（豆腐）になる。
+pub fn split_runs(spans: &[Span], chains: &[Vec<Handle<Font>>], fonts: &Assets<Font>) -> Vec<(Span, Option<Handle<Font>>)> {
+    let mut runs: Vec<(Span, Option<Handle<Font>>)> = Vec::new();
+    for (span, chain) in spans.iter().zip(chains) {
+        let data: Vec<_> = chain.iter().map(|font| fonts.get(font).map(|font| font.data.clone())).collect();
+        let faces: Vec<_> = data
+            .iter()
+            .map(|data| data.as_ref().and_then(|data| ttf_parser::Face::parse(data, 0).ok()))
+            .collect();
+
+        let mut current: Option<usize> = None;
+        let mut text = String::new();
+        for c in span.text.chars() {
+            // 空白・改行は直前のフォントのまま
+            let index = if c.is_whitespace() {
+                current.unwrap_or(0)
+            } else {
+                faces
+                    .iter()
+                    .position(|face| face.as_ref().is_some_and(|face| face.glyph_index(c).is_some()))
+                    .unwrap_or(0)
+            };
+            if current.is_some_and(|current| current != index) {
+                push_run(&mut runs, span, std::mem::take(&mut text), current, chain);
+            }
+            current = Some(index);
+            text.push(c);
+        }
+        push_run(&mut runs, span, text, current, chain);
+    }
+    runs
+}
+
+fn push_run(runs: &mut Vec<(Span, Option<Handle<Font>>)>, span: &Span, text: String, index: Option<usize>, chain: &[Handle<Font>]) {
+    if text.is_empty() {
+        return;
+    }
+    // 先頭（エントリのフォント）の場合は上書きしない
+    let font = index.filter(|&index| index > 0).map(|index| chain[index].clone());
+    runs.push((Span { text, ..span.clone() }, font));
+}
+
+// 代替フォントを選ぶ前のテキスト
+#[derive(Component)]
+pub struct FontFallbackPending;
+
+// フォントの読み込みが終わったら、文字ごとに代替フォントを選んで作り直す
+pub fn font_fallback_system(
+    mut cmds: Commands,
+    asset_server: Res<AssetServer>,
+    fonts: Res<Assets<Font>>,
+    query: Query<(Entity, &StyledText), With<FontFallbackPending>>,
+) {
+    for (entity, styled_text) in &query {
+        let chains: Vec<_> = styled_text.spans.iter().map(|span| styled_text.style.font_chain(span)).collect();
+        let loading = chains
+            .iter()
+            .flatten()
+            .any(|font| matches!(asset_server.get_load_state(font), Some(LoadState::Loading)));
+        if loading {
+            continue;
+        }
+        let runs = split_runs(&styled_text.spans, &chains, &fonts);
+        render_runs(&mut cmds.entity(entity), &runs, &styled_text.style);
+        cmds.entity(entity).remove::<FontFallbackPending>();
+    }
+}
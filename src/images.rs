@@ -0,0 +1,173 @@
+use bevy::{prelude::*, sprite::Anchor, text::TextLayoutInfo};
+use std::path::Path;
+
+use crate::fonts::{self, FontFallbackPending};
+use crate::loader::{Config, ImageFit, TextOrientation};
+use crate::regions::{Region, RegionId, Regions};
+
+// テキストと画像の間隔（px）
+const GAP: f32 = 40.0;
+
+// エントリの画像（image 列）
+#[derive(Clone)]
+pub struct ImageSource {
+    pub handle: Handle<Image>,
+    pub fit: ImageFit,
+}
+
+impl ImageSource {
+    // 画像はデータディレクトリ（~/ebb/images/... など）から読み込む
+    pub fn load(path: &str, fit: ImageFit, data_dir: &Path, asset_server: &AssetServer) -> Self {
+        if !data_dir.join(path).is_file() {
+            println!("Image not found: {:?}", data_dir.join(path));
+        }
+        Self {
+            handle: asset_server.load(fonts::asset_path(data_dir, path)),
+            fit,
+        }
+    }
+}
+
+// 画像を含むエントリ（画像は子の Sprite、テキストは画像の後ろに並べる）
+#[derive(Component)]
+pub struct EntryImage {
+    pub source: ImageSource,
+    pub scrolling: bool,
+    pub orientation: TextOrientation,
+    pub anchor: Vec2,         // 画像とテキストを合わせた全体の基準点
+    pub block: Option<Rect>,  // 全体の範囲（画像の読み込み後に決まる）
+}
+
+#[derive(Component)]
+pub struct ImageSprite;
+
+pub fn insert_image(entity: &mut EntityCommands, source: &ImageSource, scrolling: bool, orientation: TextOrientation, anchor: Anchor) {
+    entity.insert((
+        EntryImage {
+            source: source.clone(),
+            scrolling,
+            orientation,
+            anchor: anchor.as_vec(),
+            block: None,
+        },
+        anchor,
+    ));
+}
+
+// テキスト（と画像）が表示される範囲（Transform の位置が原点、大きさが決まるまでは None）
+pub fn content_rect(info: &TextLayoutInfo, anchor: Option<&Anchor>, image: Option<&EntryImage>) -> Option<Rect> {
+    match image {
+        Some(image) => image.block,
+        None if info.size.x > 0.0 && info.size.y > 0.0 => {
+            let anchor = anchor.map_or(Vec2::ZERO, |anchor| anchor.as_vec());
+            Some(Rect::from_center_size(-anchor * info.size, info.size))
+        }
+        None => None,
+    }
+}
+
+// 画像の表示する大きさ
+fn image_size(image: &EntryImage, native: Vec2, text: Option<Vec2>, region: &Region, config: &Config) -> Vec2 {
+    let (screen, safe) = (region.size(), region.safe_area(config));
+    // 並べる向きと直交する長さ（横書きなら高さ）を揃える
+    let vertical = image.orientation == TextOrientation::Vertical;
+    let cross = |size: Vec2| if vertical { size.x } else { size.y };
+    let match_cross = |target: f32| native * (target / cross(native));
+    match (image.source.fit, text) {
+        (ImageFit::Native, _) => native,
+        (ImageFit::Fit, Some(text)) => match_cross(cross(text)),
+        (ImageFit::Fit, None) if image.scrolling => match_cross(cross(safe)),
+        (ImageFit::Fit, None) => {
+            let safe_area = safe / native;
+            native * safe_area.x.min(safe_area.y)
+        }
+        (ImageFit::Fill, Some(_)) => match_cross(cross(screen)),
+        (ImageFit::Fill, None) if image.scrolling => match_cross(cross(screen)),
+        (ImageFit::Fill, None) => {
+            let screen = screen / native;
+            native * screen.x.max(screen.y)
+        }
+    }
+}
+
+// 画像の読み込み後、画像とテキストの配置を決める（テキストが作り直された場合も合わせ直す）
+#[allow(clippy::type_complexity)]
+pub fn image_layout_system(
+    mut cmds: Commands,
+    config: Res<Config>,
+    regions: Res<Regions>,
+    image_assets: Res<Assets<Image>>,
+    mut query: Query<
+        (Entity, &mut EntryImage, &TextLayoutInfo, &mut Anchor, Option<&Children>, Option<&RegionId>),
+        Without<FontFallbackPending>,
+    >,
+    mut sprites: Query<(&mut Sprite, &mut Transform), With<ImageSprite>>,
+) {
+    for (entity, mut image, info, mut anchor, children, region) in &mut query {
+        let region = regions.get(region.copied().unwrap_or_default());
+        let Some(asset) = image_assets.get(&image.source.handle) else {
+            continue;
+        };
+        let text = (info.size.x > 0.0 && info.size.y > 0.0).then_some(info.size);
+        let size = image_size(&image, asset.size_f32(), text, region, &config);
+
+        // 全体の大きさと、その中での画像・テキストの中心（画像が先）
+        let (block_size, image_center, text_center) = match (text, image.orientation) {
+            (None, _) => (size, Vec2::ZERO, Vec2::ZERO),
+            (Some(text), TextOrientation::Horizontal) => {
+                let block = Vec2::new(size.x + GAP + text.x, size.y.max(text.y));
+                (block, Vec2::new((size.x - block.x) / 2.0, 0.0), Vec2::new((block.x - text.x) / 2.0, 0.0))
+            }
+            (Some(text), TextOrientation::Vertical) => {
+                let block = Vec2::new(size.x.max(text.x), size.y + GAP + text.y);
+                (block, Vec2::new(0.0, (block.y - size.y) / 2.0), Vec2::new(0.0, (text.y - block.y) / 2.0))
+            }
+        };
+        let block_center = -image.anchor * block_size;
+        let block = Rect::from_center_size(block_center, block_size);
+        if image.block != Some(block) {
+            image.block = Some(block);
+        }
+
+        // テキストの中心が text_center になるよう基準点を動かす
+        let text_anchor = match text {
+            Some(text) => -(block_center + text_center) / text,
+            None => Vec2::ZERO,
+        };
+        if anchor.as_vec() != text_anchor {
+            *anchor = Anchor(text_anchor);
+        }
+
+        let translation = (block_center + image_center).extend(0.0);
+        let sprite = children
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .find(|child| sprites.contains(*child));
+        match sprite.and_then(|sprite| sprites.get_mut(sprite).ok()) {
+            Some((mut sprite, mut transform)) => {
+                if sprite.custom_size != Some(size) {
+                    sprite.custom_size = Some(size);
+                }
+                if transform.translation != translation {
+                    transform.translation = translation;
+                }
+            }
+            None => {
+                // テキストを作り直すと子は削除されるため、その都度追加する
+                let sprite = cmds
+                    .spawn((
+                        Sprite {
+                            image: image.source.handle.clone(),
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        Transform::from_translation(translation),
+                        region.id.layer(),
+                        ImageSprite,
+                    ))
+                    .id();
+                cmds.entity(entity).add_child(sprite);
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::{
+    asset::{embedded_asset, embedded_path, AssetPath},
+    camera::{visibility::RenderLayers, ScalingMode},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderType},
+    shader::ShaderRef,
+    sprite_render::{Material2d, Material2dPlugin},
+};
+
+use crate::capture;
+use crate::loader::{Config, LedConfig};
+use crate::regions::{RegionId, Regions};
+use crate::{LoopingText, TextScroll};
+
+// LED 一つあたりの描画ピクセル数（縦横、平均して LED の明るさにする）
+const SAMPLES: u32 = 4;
+
+// LED の表示だけを写すレイヤー（領域のレイヤーと重ならない番号）
+const LED_LAYER: usize = 63;
+
+// 画面を描画した画像を LED の点として表示するマテリアル
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct LedMaterial {
+    #[uniform(0)]
+    settings: LedSettings,
+    #[texture(1)]
+    screen: Handle<Image>,
+}
+
+#[derive(ShaderType, Clone)]
+struct LedSettings {
+    grid: Vec2, // 列数・行数
+    dot: f32,
+    glow: f32,
+    off_color: LinearRgba,
+    samples: u32,
+}
+
+impl Material2d for LedMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(AssetPath::from_path_buf(embedded_path!("led.wgsl")).with_source("embedded"))
+    }
+}
+
+pub fn register(app: &mut App) {
+    embedded_asset!(app, "led.wgsl");
+    app.add_plugins(Material2dPlugin::<LedMaterial>::default());
+}
+
+// メインのウィンドウのカメラが描画する画像（LED 一つにつき SAMPLES × SAMPLES ピクセル）
+#[derive(Resource)]
+pub struct LedScreen(pub Handle<Image>);
+
+pub fn create_screen(led: &LedConfig, images: &mut Assets<Image>) -> LedScreen {
+    let size = UVec2::new(led.columns, led.rows) * SAMPLES;
+    LedScreen(capture::render_target(size, images))
+}
+
+// 画像を LED の点としてウィンドウに表示する（エラーバナーなどの UI もこのカメラに重ねる）
+pub fn spawn_display(
+    cmds: &mut Commands,
+    led: &LedConfig,
+    screen: &LedScreen,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<LedMaterial>,
+) {
+    let grid = Vec2::new(led.columns as f32, led.rows as f32);
+    let off_color = led.off_color.as_deref().and_then(crate::text_spawner::parse_color);
+    let material = LedMaterial {
+        settings: LedSettings {
+            grid,
+            dot: led.dot.unwrap_or(0.8),
+            glow: led.glow.unwrap_or(0.5),
+            off_color: off_color.unwrap_or(Color::srgb_u8(0x1a, 0x1a, 0x1a)).into(),
+            samples: SAMPLES,
+        },
+        screen: screen.0.clone(),
+    };
+    cmds.spawn((
+        Camera2d,
+        Camera {
+            order: 100,
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..default()
+        },
+        // LED が丸く見えるよう縦横比を保ち、余った部分は黒にする
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: grid.x,
+                min_height: grid.y,
+            },
+            ..OrthographicProjection::default_2d()
+        }),
+        RenderLayers::layer(LED_LAYER),
+        IsDefaultUiCamera,
+    ));
+    cmds.spawn((
+        Mesh2d(meshes.add(Rectangle::from_size(grid))),
+        MeshMaterial2d(materials.add(material)),
+        RenderLayers::layer(LED_LAYER),
+    ));
+}
+
+// スクロールするテキストを LED の列・行の単位で動かす（Transform はそのまま、描画する位置だけ揃える）
+#[allow(clippy::type_complexity)]
+pub fn snap_system(
+    config: Res<Config>,
+    regions: Res<Regions>,
+    mut query: Query<(&mut GlobalTransform, Option<&Children>, Option<&RegionId>), Or<(With<TextScroll>, With<LoopingText>)>>,
+    mut children_query: Query<&mut GlobalTransform, (Without<TextScroll>, Without<LoopingText>)>,
+) {
+    let Some(led) = &config.led else {
+        return;
+    };
+    for (mut global, children, region) in &mut query {
+        let region = regions.get(region.copied().unwrap_or_default());
+        if region.window != 0 {
+            continue;
+        }
+        let display = &regions.displays[0];
+        let pitch = display.size / Vec2::new(led.columns as f32, led.rows as f32);
+        // 画面の左上（LED の格子の角）の位置
+        let origin = Vec2::new(display.camera_offset - region.rect.center().x, region.rect.center().y);
+        let position = global.translation().truncate();
+        let snapped = origin + ((position - origin) / pitch).round() * pitch;
+        let delta = (snapped - position).extend(0.0);
+        if delta == Vec3::ZERO {
+            continue;
+        }
+        *global = move_global(&global, delta);
+        for child in children.into_iter().flat_map(|children| children.iter()) {
+            if let Ok(mut child_global) = children_query.get_mut(child) {
+                *child_global = move_global(&child_global, delta);
+            }
+        }
+    }
+}
+
+fn move_global(global: &GlobalTransform, delta: Vec3) -> GlobalTransform {
+    let mut affine = global.affine();
+    affine.translation += Vec3A::from(delta);
+    affine.into()
+}
@@ -0,0 +1,55 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+struct LedSettings {
+    grid: vec2<f32>,
+    dot: f32,
+    glow: f32,
+    off_color: vec4<f32>,
+    samples: u32,
+};
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> settings: LedSettings;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var screen: texture_2d<f32>;
+
+// LED 一つ分のピクセルの平均
+fn led_color(cell: vec2<i32>) -> vec3<f32> {
+    let n = i32(settings.samples);
+    let grid = vec2<i32>(settings.grid);
+    let clamped = clamp(cell, vec2<i32>(0), grid - vec2<i32>(1));
+    var sum = vec3<f32>(0.0);
+    for (var y = 0; y < n; y++) {
+        for (var x = 0; x < n; x++) {
+            sum += textureLoad(screen, clamped * n + vec2<i32>(x, y), 0).rgb;
+        }
+    }
+    return sum / f32(n * n);
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let position = mesh.uv * settings.grid;
+    let cell = vec2<i32>(floor(position));
+    let local = fract(position) - vec2<f32>(0.5);
+    let radius = settings.dot * 0.5;
+
+    // 点灯している LED の丸
+    let distance = length(local);
+    let edge = max(fwidth(distance), 0.001);
+    let disc = 1.0 - smoothstep(radius - edge, radius, distance);
+    let color = led_color(cell);
+    let lit = max(color, settings.off_color.rgb);
+    var result = lit * disc;
+
+    // 周りの LED からのにじみ（上下左右と斜めの 8 個）
+    if settings.glow > 0.0 {
+        for (var dy = -1; dy <= 1; dy++) {
+            for (var dx = -1; dx <= 1; dx++) {
+                let offset = vec2<f32>(f32(dx), f32(dy));
+                let d = length(local - offset);
+                let halo = exp(-d * d / (radius * radius * 0.8)) * settings.glow * 0.6;
+                result += led_color(cell + vec2<i32>(dx, dy)) * halo * (1.0 - disc);
+            }
+        }
+    }
+    return vec4<f32>(result, 1.0);
+}
@@ -1,144 +1,1189 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Debug, Resource, Clone)]
+use crate::cli::Cli;
+use crate::diagnostics::Report;
+use crate::preset_file::{self, PresetFormat};
+use toml::de::{DeTable, DeValue};
+
+#[derive(Serialize, Deserialize, Debug, Resource, Clone, Default)]
 pub struct TextSource {
     pub content: String,
     pub duration: f32,
+    // 以下は任意列（空欄・列なしの場合は既定の見た目）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<TextAlign>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effect: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<TextOrientation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<ScrollDirection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit: Option<AutoFit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition: Option<TransitionKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>, // データディレクトリ基準の画像ファイル
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_fit: Option<ImageFit>,
+}
+
+impl TextSource {
+    pub fn new(content: &str, duration: f32) -> Self {
+        Self {
+            content: content.to_string(),
+            duration,
+            ..default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+// 横書き・縦書き
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOrientation {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+// 静止テキストの大きさの自動調整
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoFit {
+    #[default]
+    Off,
+    Shrink, // はみ出す場合だけ小さくする
+    Fit,    // 表示領域いっぱいに拡大・縮小する
+}
+
+// 画像の大きさの決め方
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFit {
+    #[default]
+    Fit,    // 表示領域に収める（テキストと並べる場合は文字の高さに合わせる）
+    Fill,   // 画面を埋める（スクロールする場合は画面の高さに合わせる）
+    Native, // 画像の画素数のまま
+}
+
+// 表示の切り替え方（現れるときと消えるとき）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    #[default]
+    None,
+    Fade,  // 透明度
+    Slide, // 進行方向の反対側から入り、進行方向へ出ていく
+    Wipe,  // 左から右へ現れ、左から右へ消える
+    Zoom,  // 拡大して現れ、縮小して消える
+    Flip,  // 横方向に裏返る
+}
+
+// スクロールの向き（テキストが動く向き）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Component)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollDirection {
+    RightToLeft,
+    LeftToRight,
+    TopToBottom,
+    BottomToTop,
+}
+
+impl ScrollDirection {
+    // 指定がない場合、横書きは右から左、縦書きは上から下
+    pub fn default_for(orientation: TextOrientation) -> Self {
+        match orientation {
+            TextOrientation::Horizontal => Self::RightToLeft,
+            TextOrientation::Vertical => Self::TopToBottom,
+        }
+    }
+
+    // 進行方向の単位ベクトル
+    pub fn vector(self) -> Vec2 {
+        match self {
+            Self::RightToLeft => Vec2::NEG_X,
+            Self::LeftToRight => Vec2::X,
+            Self::TopToBottom => Vec2::NEG_Y,
+            Self::BottomToTop => Vec2::Y,
+        }
+    }
+
+    // テキスト・画面の大きさのうち進行方向の長さ
+    pub fn along(self, size: Vec2) -> f32 {
+        match self {
+            Self::RightToLeft | Self::LeftToRight => size.x,
+            Self::TopToBottom | Self::BottomToTop => size.y,
+        }
+    }
+}
+
+// メインのウィンドウの名前（[[regions]] の window、WebSocket の window に使う）
+pub const MAIN_WINDOW: &str = "main";
+
+// window_width を指定しない場合の画面の幅（ウィンドウに合わせるまでと、LED・領域・画像に描画する場合に使う）
+pub const DEFAULT_WINDOW_WIDTH: f32 = 1920.0;
+
+// window_width・window_height（高さは未指定なら幅の 9/16）
+fn screen_size(window_width: Option<f32>, window_height: Option<f32>) -> Vec2 {
+    let width = window_width.unwrap_or(DEFAULT_WINDOW_WIDTH);
+    Vec2::new(width, window_height.unwrap_or(width * 9.0 / 16.0))
 }
 
 #[derive(Deserialize, Debug, Resource, Default)]
 pub struct Config {
     pub text_size: f32,
-    pub window_width: f32,
+    #[serde(default)]
+    pub window_width: Option<f32>, // 指定するとウィンドウの大きさによらずこの幅の画面にする（ウィンドウより大きい LED の画面など）
     pub camera_offset: f32,
+    #[serde(default)]
+    pub presets_dir: Option<PathBuf>, // 相対パスはデータディレクトリ基準
+    #[serde(default)]
+    pub listen: Option<String>,
+    #[serde(default)]
+    pub default_preset: Option<String>,
+    #[serde(default)]
+    pub presets: Vec<PresetInfo>, // 並び順・表示名（[[presets]]）
+    #[serde(default)]
+    pub normalize_width: bool, // 全角英数字を半角に、半角カナを全角に揃える
+    #[serde(default)]
+    pub bold_font: Option<String>, // **太字** に使うフォント（[fonts] と同じくパスか名前、未指定なら通常のフォント）
+    #[serde(default)]
+    pub fonts: FontConfig,
+    #[serde(default)]
+    pub auto_fit: AutoFitConfig,
+    #[serde(default)]
+    pub window_height: Option<f32>, // window_width と一緒に指定する画面の高さ（未指定なら幅の 9/16）
+    #[serde(default)]
+    pub orientation: TextOrientation, // エントリで指定がない場合の横書き・縦書き
+    #[serde(default)]
+    pub direction: Option<ScrollDirection>, // エントリで指定がない場合のスクロールの向き
+    #[serde(default)]
+    pub transition: TransitionConfig,
+    #[serde(default)]
+    pub bingo_effect: Option<String>, // ビンゴの数字の効果（effect 列と同じ書き方）
+    #[serde(default)]
+    pub regions: Vec<RegionConfig>, // 画面の分割（なければ画面全体で一つ）
+    #[serde(default)]
+    pub monitor: Option<usize>, // メインのウィンドウを表示するモニターの番号（指定するとフルスクリーン）
+    #[serde(default)]
+    pub windows: Vec<WindowConfig>, // メインのウィンドウ以外のウィンドウ
+    #[serde(default)]
+    pub led: Option<LedConfig>, // メインのウィンドウを LED の点で表示する
+    #[serde(default)]
+    pub outputs: Vec<OutputConfig>, // メインのウィンドウの内容を送る LED パネルなど
+}
+
+// config.toml の [fonts]（パスはデータディレクトリ、なければ assets 基準）
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FontConfig {
+    #[serde(default)]
+    pub primary: Option<String>, // 未指定なら fonts/ipag.ttf
+    #[serde(default)]
+    pub fallback: Vec<String>, // 通常のフォントに字形がない文字に順に試すフォント
+    #[serde(default)]
+    pub named: BTreeMap<String, String>, // 名前 → パス（エントリの font 列で名前を指定できる）
+}
+
+// config.toml の [auto_fit]
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AutoFitConfig {
+    #[serde(default)]
+    pub mode: AutoFit, // エントリの fit 列で上書きできる
+    #[serde(default)]
+    pub safe_area: Option<f32>, // 表示領域の画面に対する割合（未指定なら 0.9）
+    #[serde(default)]
+    pub wrap: bool, // 表示領域の幅で折り返す
+}
+
+// config.toml の [led]（columns × rows 個の LED で表示する）
+#[derive(Deserialize, Debug, Clone)]
+pub struct LedConfig {
+    pub columns: u32,
+    pub rows: u32,
+    #[serde(default)]
+    pub dot: Option<f32>, // LED の直径（間隔に対する割合、未指定なら 0.8）
+    #[serde(default)]
+    pub glow: Option<f32>, // 光のにじみの強さ（0〜1、未指定なら 0.5）
+    #[serde(default)]
+    pub off_color: Option<String>, // 消えている LED の色（未指定なら #1a1a1a）
+}
+
+// config.toml の [[outputs]] の一項目（メインのウィンドウの内容を width × height 画素にして送る）
+#[derive(Deserialize, Debug, Clone)]
+pub struct OutputConfig {
+    pub protocol: OutputProtocol,
+    #[serde(default)]
+    pub host: Option<String>, // 送信先（artnet は必須、sacn は未指定ならマルチキャスト）
+    #[serde(default)]
+    pub device: Option<String>, // serial の書き込み先（/dev/ttyACM0 など、ファイル・pty でもよい）
+    #[serde(default)]
+    pub baud: Option<u32>, // serial の通信速度（未指定なら 115200）
+    #[serde(default)]
+    pub format: SerialFormat, // serial の画素の形式
+    #[serde(default)]
+    pub port: Option<u16>, // 未指定なら artnet は 6454、sacn は 5568
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub area: Option<AreaConfig>, // 送る画面の範囲（未指定なら画面全体）
+    #[serde(default)]
+    pub universe: Option<u16>, // 最初のユニバース（未指定なら artnet は 0、sacn は 1）
+    #[serde(default)]
+    pub channels: Option<u16>, // 1ユニバースに使うチャンネル数（未指定なら 510 = 170 画素）
+    #[serde(default)]
+    pub serpentine: bool, // 行ごとに配線の向きが折り返す
+    #[serde(default)]
+    pub color_order: Option<String>, // "rgb"、"grb" など（未指定なら rgb）
+    #[serde(default)]
+    pub gamma: Option<f32>, // 未指定なら 2.2
+    #[serde(default)]
+    pub brightness: Option<f32>, // 0〜1（未指定なら 1）
+    #[serde(default)]
+    pub fps: Option<f32>, // 1秒に送る回数（未指定なら 30）
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputProtocol {
+    Artnet,
+    Sacn,
+    Serial,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialFormat {
+    #[default]
+    Rgb888,
+    Rgb565,
+}
+
+// 画面の左上を原点とした範囲（px）
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AreaConfig {
+    #[serde(default)]
+    pub x: f32,
+    #[serde(default)]
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl OutputConfig {
+    // 送る画面の範囲（画面の左上を原点とした px）
+    pub fn area(&self, screen: Vec2) -> Rect {
+        match self.area {
+            Some(area) => Rect::new(area.x, area.y, area.x + area.width, area.y + area.height),
+            None => Rect::from_corners(Vec2::ZERO, screen),
+        }
+    }
+
+    // 1ユニバースの画素数（画素が二つのユニバースにまたがらないようにする）
+    pub fn pixels_per_universe(&self) -> usize {
+        self.channels.unwrap_or(510) as usize / 3
+    }
+
+    pub fn first_universe(&self) -> u16 {
+        self.universe.unwrap_or(match self.protocol {
+            OutputProtocol::Sacn => 1,
+            OutputProtocol::Artnet | OutputProtocol::Serial => 0,
+        })
+    }
+
+    pub fn universe_count(&self) -> usize {
+        ((self.width * self.height) as usize).div_ceil(self.pixels_per_universe())
+    }
+}
+
+// config.toml の [transition]
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TransitionConfig {
+    #[serde(default)]
+    pub kind: TransitionKind, // エントリの transition 列で上書きできる
+    #[serde(default)]
+    pub duration: Option<f32>, // 秒（未指定なら 0.4）
+}
+
+// config.toml の [[regions]] の一項目（位置・大きさは画面の左上を原点とした px）
+#[derive(Deserialize, Debug, Clone)]
+pub struct RegionConfig {
+    pub name: String,
+    #[serde(default)]
+    pub x: f32,
+    #[serde(default)]
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub preset: Option<String>, // 未指定ならデフォルトのプリセット
+    #[serde(default)]
+    pub text_size: Option<f32>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub autoplay: bool, // 表示が終わったら次のエントリを自動で表示する
+    #[serde(default)]
+    pub hold: Option<f32>, // autoplay で静止・ループするエントリを表示する秒数（未指定なら 5）
+    #[serde(default)]
+    pub window: Option<String>, // 表示するウィンドウの名前（未指定ならメインのウィンドウ）
+}
+
+// config.toml の [[windows]] の一項目（preset などは [[regions]] がない場合に使う）
+#[derive(Deserialize, Debug, Clone)]
+pub struct WindowConfig {
+    pub name: String,
+    #[serde(default)]
+    pub monitor: Option<usize>,
+    #[serde(default)]
+    pub window_width: Option<f32>,
+    #[serde(default)]
+    pub window_height: Option<f32>,
+    #[serde(default)]
+    pub camera_offset: f32,
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub text_size: Option<f32>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub autoplay: bool,
+    #[serde(default)]
+    pub hold: Option<f32>,
+}
+
+impl WindowConfig {
+    pub fn screen_size(&self) -> Vec2 {
+        screen_size(self.window_width, self.window_height)
+    }
+}
+
+// config.toml の [[presets]] の一項目
+#[derive(Deserialize, Debug, Clone)]
+pub struct PresetInfo {
+    pub name: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// プリセットファイル自体に書かれた表示名・説明（TOML/JSON/YAML のみ）
+#[derive(Debug, Clone, Default)]
+pub struct PresetMeta {
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub meta: PresetMeta,
+    pub texts: Vec<TextSource>,
 }
 
+impl Preset {
+    pub fn new(name: &str, texts: Vec<TextSource>) -> Self {
+        Self::with_meta(name, PresetMeta::default(), texts)
+    }
+
+    pub fn with_meta(name: &str, meta: PresetMeta, texts: Vec<TextSource>) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            description: None,
+            meta,
+            texts,
+        }
+    }
+}
+
+// プリセットの一覧（config.toml の [[presets]] の順、その後はファイル名順）
 #[derive(Resource)]
 pub struct PresetManager {
-    pub presets: HashMap<String, Vec<TextSource>>,
+    pub presets: Vec<Preset>,
 }
 
-pub fn load_csv(file: &str) -> Result<Vec<TextSource>, Box<dyn Error>> {
-    let mut csv_path = std::env::home_dir().unwrap();
-    csv_path.push("ebb/presets/".to_string() + file);
-    let file_content = std::fs::read_to_string(csv_path)?;
+impl PresetManager {
+    pub fn new(presets: Vec<Preset>, config: &Config) -> Self {
+        let mut manager = Self { presets };
+        manager.apply_config(config);
+        manager
+    }
+
+    // 並び順・表示名・説明を config.toml に合わせる（config.toml > プリセットファイル > ファイル名）
+    pub fn apply_config(&mut self, config: &Config) {
+        for preset in &mut self.presets {
+            let info = config.presets.iter().find(|info| info.name == preset.name);
+            preset.display_name = info
+                .and_then(|info| info.display_name.clone())
+                .or_else(|| preset.meta.display_name.clone())
+                .unwrap_or_else(|| preset.name.clone());
+            preset.description = info
+                .and_then(|info| info.description.clone())
+                .or_else(|| preset.meta.description.clone());
+        }
+        let position = |name: &str| {
+            config.presets.iter().position(|info| info.name == name).unwrap_or(usize::MAX)
+        };
+        self.presets.sort_by(|a, b| {
+            position(&a.name).cmp(&position(&b.name)).then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.presets.iter().map(|preset| preset.name.clone()).collect()
+    }
+
+    // default_preset で指定されたもの、なければ先頭のプリセット
+    pub fn default_preset(&self, config: &Config) -> Option<&Preset> {
+        config
+            .default_preset
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.presets.first())
+    }
 
-    let rdr = csv::ReaderBuilder::new()
+    // config.toml で指定されたプリセットが存在するか検査
+    pub fn check_config(&self, config: &Config, conf_path: &Path, report: &mut Report) {
+        if let Some(name) = &config.default_preset
+            && self.get(name).is_none()
+        {
+            report.warning(conf_path, None, format!("default_preset `{}` not found", name));
+        }
+        for info in &config.presets {
+            if self.get(&info.name).is_none() {
+                report.warning(conf_path, None, format!("preset `{}` in [[presets]] not found", info.name));
+            }
+        }
+        for region in &config.regions {
+            if let Some(preset) = &region.preset
+                && self.get(preset).is_none()
+            {
+                report.warning(conf_path, None, format!("preset `{}` of region `{}` not found", preset, region.name));
+            }
+        }
+        for window in &config.windows {
+            if let Some(preset) = &window.preset
+                && self.get(preset).is_none()
+            {
+                report.warning(conf_path, None, format!("preset `{}` of window `{}` not found", preset, window.name));
+            }
+        }
+        // bold_font がないと **太字** は通常のフォントのまま表示される
+        if config.bold_font.is_none() {
+            let bold: Vec<&str> = self
+                .presets
+                .iter()
+                .filter(|preset| {
+                    preset.texts.iter().any(|text| crate::markup::parse(&text.content).iter().any(|span| span.bold))
+                })
+                .map(|preset| preset.name.as_str())
+                .collect();
+            if !bold.is_empty() {
+                report.warning(
+                    conf_path,
+                    None,
+                    format!("`**bold**` in preset `{}` needs `bold_font`, shown in the regular font", bold.join("`, `")),
+                );
+            }
+        }
+    }
+}
+
+// データディレクトリ・設定ファイル・プリセットディレクトリの実際のパス
+#[derive(Resource, Debug, Clone)]
+pub struct DataPaths {
+    pub data_dir: PathBuf,
+    pub config: PathBuf,
+    pub presets: PathBuf,
+}
+
+impl DataPaths {
+    pub fn new(cli: &Cli) -> Self {
+        let data_dir = match &cli.data_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let mut dir = std::env::home_dir().unwrap_or_default();
+                dir.push("ebb");
+                dir
+            }
+        };
+        let config = cli.config.clone().unwrap_or_else(|| data_dir.join("config.toml"));
+        let presets = data_dir.join("presets");
+        Self { data_dir, config, presets }
+    }
+
+    // config.toml の presets_dir を反映（コマンドラインの指定は Cli::apply で反映済み）
+    pub fn apply_config(&mut self, config: &Config) {
+        self.presets = match &config.presets_dir {
+            Some(dir) => self.data_dir.join(dir),
+            None => self.data_dir.join("presets"),
+        };
+    }
+}
+
+// CSV の列名（ヘッダーの検査に使用）
+pub const TEXT_SOURCE_COLUMNS: &[&str] = &[
+    "content", "duration", "color", "background", "font_size", "font", "align", "effect",
+    "orientation", "direction", "fit", "transition", "image", "image_fit",
+];
+
+// 表示できる画像の拡張子
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+pub fn load_csv(csv_path: &Path, report: &mut Report) -> Result<Vec<TextSource>, Box<dyn Error>> {
+    let (file_content, encoding) = crate::encoding::read_text(csv_path)?;
+    println!("Reading {:?} as {}", csv_path, encoding);
+
+    let mut rdr = csv::ReaderBuilder::new()
         .has_headers(true)
+        .delimiter(crate::encoding::detect_delimiter(&file_content))
         .from_reader(file_content.as_bytes());
 
-    let result: Vec<TextSource> = rdr
-        .into_deserialize()
-        .collect::<Result<Vec<TextSource>, csv::Error>>()?;
-    Ok(result)
+    let headers = rdr.headers()?.clone();
+    let records = rdr.records().map(|record| match record {
+        Ok(record) => (record.position().map(|p| p.line() as usize), Ok(record)),
+        Err(e) => (e.position().map(|p| p.line() as usize), Err(e.to_string())),
+    });
+    Ok(load_records(csv_path, &headers, Some(1), records, report))
 }
 
-pub fn load_all_presets() -> Result<HashMap<String, Vec<TextSource>>, Box<dyn Error>> {
-    let mut presets_path = std::env::home_dir().unwrap();
-    presets_path.push("ebb/presets");
-    
-    let mut presets = HashMap::new();
-    
-    if !presets_path.exists() {
-        println!("Warning: Presets directory not found at {:?}", presets_path);
-        return Ok(presets);
-    }
-    
-    let entries = std::fs::read_dir(&presets_path)?;
-    
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if let Some(extension) = path.extension() {
-            if extension == "csv" {
-                if let Some(file_name) = path.file_stem() {
-                    if let Some(preset_name) = file_name.to_str() {
-                        match load_csv(&format!("{}.csv", preset_name)) {
-                            Ok(texts) => {
-                                println!("Loaded preset '{}' with {} texts", preset_name, texts.len());
-                                presets.insert(preset_name.to_string(), texts);
-                            }
-                            Err(e) => {
-                                println!("Failed to load preset '{}': {}", preset_name, e);
-                            }
+// ヘッダー行と各行（行番号付き）から TextSource を作る（CSV・スプレッドシート共通）
+pub fn load_records(
+    path: &Path,
+    headers: &csv::StringRecord,
+    header_line: Option<usize>,
+    records: impl Iterator<Item = (Option<usize>, Result<csv::StringRecord, String>)>,
+    report: &mut Report,
+) -> Vec<TextSource> {
+    let mut missing_column = false;
+    for column in ["content", "duration"] {
+        if !headers.iter().any(|h| h == column) {
+            report.error(path, header_line, format!("missing column `{}`", column));
+            missing_column = true;
+        }
+    }
+    for header in headers.iter() {
+        if !TEXT_SOURCE_COLUMNS.contains(&header) {
+            report.warning(path, header_line, format!("unknown column `{}`", header));
+        }
+    }
+    if missing_column {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    for (line, record) in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                report.error(path, line, e);
+                continue;
+            }
+        };
+        match record.deserialize::<TextSource>(Some(headers)) {
+            Ok(text_source) => {
+                // エラーのある行は読み飛ばす
+                if validate_text_source(&text_source, path, line, report) {
+                    result.push(text_source);
+                }
+            }
+            Err(e) => {
+                let message = match e.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => {
+                        match err.field().and_then(|i| headers.get(i as usize)) {
+                            Some(column) => format!("column `{}`: {}", column, err.kind()),
+                            None => err.to_string(),
                         }
                     }
+                    _ => e.to_string(),
+                };
+                report.error(path, line, message);
+            }
+        }
+    }
+    result
+}
+
+// 一行分の値を検査し、表示できる場合は true を返す
+pub fn validate_text_source(text_source: &TextSource, file: &Path, line: Option<usize>, report: &mut Report) -> bool {
+    let mut ok = true;
+    // 画像だけのエントリは content が空でもよい
+    if text_source.content.trim().is_empty() && text_source.image.is_none() {
+        report.error(file, line, "empty content");
+        ok = false;
+    }
+    if let Some(image) = &text_source.image {
+        let extension = Path::new(image)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            report.error(file, line, format!("unsupported image `{}` (use PNG, JPEG or WebP)", image));
+            ok = false;
+        }
+    }
+    if !text_source.duration.is_finite() || text_source.duration < 0.0 {
+        report.error(file, line, format!("invalid duration {} (must be 0 or positive)", text_source.duration));
+        ok = false;
+    }
+    if let Some(font_size) = text_source.font_size
+        && (font_size.is_nan() || font_size <= 0.0)
+    {
+        report.error(file, line, format!("invalid font_size {} (must be positive)", font_size));
+        ok = false;
+    }
+    for color in [&text_source.color, &text_source.background].into_iter().flatten() {
+        if crate::text_spawner::parse_color(color).is_none() {
+            report.warning(file, line, format!("unknown color `{}`", color));
+        }
+    }
+    if let Some(effect) = &text_source.effect
+        && let Err(e) = crate::effects::parse(effect)
+    {
+        report.warning(file, line, e);
+    }
+    ok
+}
+
+// 拡張子に応じて CSV / TOML / JSON / YAML のプリセットを読み込む（スプレッドシートは load_all_presets で扱う）
+pub fn load_preset_file(path: &Path, config: &Config, report: &mut Report) -> Result<(PresetMeta, Vec<TextSource>), Box<dyn Error>> {
+    let (meta, texts) = match PresetFormat::from_path(path) {
+        Some(PresetFormat::Csv) => (PresetMeta::default(), load_csv(path, report)?),
+        Some(PresetFormat::Spreadsheet) => {
+            return Err("spreadsheets contain one preset per sheet, export a sheet as CSV first".into());
+        }
+        Some(format) => preset_file::load(path, format, report)?,
+        None => return Err("unsupported preset file extension".into()),
+    };
+    Ok((meta, normalize_texts(texts, config)))
+}
+
+fn normalize_texts(mut texts: Vec<TextSource>, config: &Config) -> Vec<TextSource> {
+    if config.normalize_width {
+        for text_source in &mut texts {
+            text_source.content = crate::text::normalize_width(&text_source.content);
+        }
+    }
+    texts
+}
+
+// ファイル名順のプリセット一覧
+pub fn load_all_presets(presets_path: &Path, config: &Config, report: &mut Report) -> Result<Vec<Preset>, Box<dyn Error>> {
+    let mut presets = Vec::new();
+
+    if !presets_path.exists() {
+        report.warning(presets_path, None, "presets directory not found");
+    } else {
+        let mut paths = std::fs::read_dir(presets_path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+        paths.sort();
+
+        for path in paths {
+            let Some(format) = PresetFormat::from_path(&path) else {
+                continue;
+            };
+            let Some(file_stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            // Excel が開いている間に作るロックファイル
+            if file_stem.starts_with("~$") {
+                continue;
+            }
+
+            // スプレッドシートはシートごとに「ファイル名/シート名」のプリセットにする
+            let loaded = if format == PresetFormat::Spreadsheet {
+                crate::spreadsheet::load(&path, report).map(|sheets| {
+                    sheets
+                        .into_iter()
+                        .map(|(sheet_name, texts)| {
+                            let name = format!("{}/{}", file_stem, sheet_name);
+                            let meta = PresetMeta {
+                                display_name: Some(sheet_name),
+                                description: None,
+                            };
+                            (name, meta, normalize_texts(texts, config))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            } else {
+                load_preset_file(&path, config, report).map(|(meta, texts)| vec![(file_stem.to_string(), meta, texts)])
+            };
+            let loaded = match loaded {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    report.error(&path, None, format!("can't load preset: {}", e));
+                    continue;
+                }
+            };
+
+            for (preset_name, meta, texts) in loaded {
+                if presets.iter().any(|preset: &Preset| preset.name == preset_name) {
+                    report.warning(&path, None, format!("duplicate preset name `{}`, skipped", preset_name));
+                } else if texts.is_empty() {
+                    report.warning(&path, None, format!("preset `{}` has no entries, skipped", preset_name));
+                } else {
+                    println!("Loaded preset '{}' with {} texts", preset_name, texts.len());
+                    presets.push(Preset::with_meta(&preset_name, meta, texts));
                 }
             }
         }
     }
-    
+
     // デフォルトプリセットがない場合は追加
     if presets.is_empty() {
         println!("No presets found, adding default preset");
-        presets.insert("default".to_string(), vec![
-            TextSource {
-                content: "Default Demo Text 1".to_string(),
-                duration: 5.0,
-            },
-            TextSource {
-                content: "Default Demo Text 2".to_string(),
-                duration: 5.0,
-            },
-        ]);
+        presets.push(Preset::new("default", vec![
+            TextSource::new("Default Demo Text 1", 5.0),
+            TextSource::new("Default Demo Text 2", 5.0),
+        ]));
     }
-    
+
     Ok(presets)
 }
 
-pub fn load_config() -> Result<Config, Box<dyn Error>> {
-    let mut conf_path = std::env::home_dir().unwrap();
-    conf_path.push("ebb/config.toml");
-    let file_content = std::fs::read_to_string(conf_path).unwrap();
-    println!("{}", file_content);
-    let result: Config = toml::from_str(&file_content.as_str())?;
-    Ok(result)
+impl Config {
+    // 設定ファイルが読めない場合、または値が不正な場合に使う値
+    pub fn fallback() -> Self {
+        Self {
+            text_size: 1080.0,
+            camera_offset: 0.0,
+            ..default()
+        }
+    }
+
+    pub fn screen_size(&self) -> Vec2 {
+        screen_size(self.window_width, self.window_height)
+    }
+
+    // 以前は window_width が必須だったので、指定したままの設定ではウィンドウの大きさに合わせないことを知らせる
+    pub fn print_fixed_size_notes(&self) {
+        if self.window_width.is_some() && self.led.is_none() {
+            let size = self.screen_size();
+            println!(
+                "Note: window_width is set, so the main window keeps {}x{} instead of following its size (remove it to follow the window)",
+                size.x, size.y
+            );
+        }
+        for window in self.windows.iter().filter(|window| window.window_width.is_some()) {
+            let size = window.screen_size();
+            println!(
+                "Note: window_width is set for window `{}`, so it keeps {}x{} instead of following its size",
+                window.name, size.x, size.y
+            );
+        }
+    }
+
+    // 切り替えにかける秒数
+    pub fn transition_duration(&self) -> f32 {
+        self.transition.duration.unwrap_or(0.4)
+    }
 }
 
-pub fn unwrap_csv(f: &str) -> Vec<TextSource> {
-    match load_csv(f) {
-        Ok(n) => return n,
-        Err(_e) => {
-            println!("Err: Can't Load Preset File: {}", f);
-            return vec![TextSource {
-                content: "This is a Demo Text".to_string(),
-                duration: 5.0,
-            }];
+// 読み込みに失敗した場合は None（問題は report に記録される）
+pub fn load_config(conf_path: &Path, report: &mut Report) -> Option<Config> {
+    let file_content = match std::fs::read_to_string(conf_path) {
+        Ok(file_content) => file_content,
+        Err(e) => {
+            report.error(conf_path, None, format!("can't read config file: {}", e));
+            return None;
         }
     };
-}
 
-pub fn unwrap_all_presets() -> PresetManager {
-    match load_all_presets() {
-        Ok(presets) => PresetManager { presets },
+    // 構文エラーはすべて報告する
+    let (table, errors) = DeTable::parse_recoverable(&file_content);
+    if !errors.is_empty() {
+        for e in errors {
+            report_toml_error(conf_path, &file_content, &e, report);
+        }
+        return None;
+    }
+    let table = table.into_inner();
+
+    let deserializer = match toml::de::Deserializer::parse(&file_content) {
+        Ok(deserializer) => deserializer,
         Err(e) => {
-            println!("Err: Can't Load Presets: {}", e);
-            let mut default_presets = HashMap::new();
-            default_presets.insert("default".to_string(), vec![
-                TextSource {
-                    content: "This is a Demo Text".to_string(),
-                    duration: 5.0,
+            report_toml_error(conf_path, &file_content, &e, report);
+            return None;
+        }
+    };
+    let mut unknown_keys = Vec::new();
+    let result: Result<Config, _> = serde_ignored::deserialize(deserializer, |path| {
+        unknown_keys.push(path_segments(&path));
+    });
+    for segments in unknown_keys {
+        let message = format!("unknown key `{}`", segments.join("."));
+        match key_offset(&table, &segments) {
+            Some(offset) => report.warning_at(conf_path, &file_content, offset, message),
+            None => report.warning(conf_path, None, message),
+        }
+    }
+    let mut config = match result {
+        Ok(config) => config,
+        Err(e) => {
+            report_toml_error(conf_path, &file_content, &e, report);
+            return None;
+        }
+    };
+
+    // 不正な値は既定値に置き換える
+    let fallback = Config::fallback();
+    let mut check_positive = |key: &str, value: &mut f32, default: f32| {
+        if value.is_nan() || *value <= 0.0 {
+            let message = format!("`{}` must be positive (got {}), using {}", key, value, default);
+            match key_offset(&table, &[key.to_string()]) {
+                Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+                None => report.error(conf_path, None, message),
+            }
+            *value = default;
+        }
+    };
+    check_positive("text_size", &mut config.text_size, fallback.text_size);
+    if let Some(window_width) = &mut config.window_width {
+        check_positive("window_width", window_width, DEFAULT_WINDOW_WIDTH);
+    }
+    if let Some(window_height) = &mut config.window_height {
+        let default = config.window_width.unwrap_or(DEFAULT_WINDOW_WIDTH) * 9.0 / 16.0;
+        check_positive("window_height", window_height, default);
+    }
+    if let Some(listen) = &config.listen
+        && let Err(e) = listen.parse::<std::net::SocketAddr>()
+    {
+        let message = format!("invalid `listen` address `{}` ({}), using {}", listen, e, crate::server::DEFAULT_LISTEN);
+        match key_offset(&table, &["listen".to_string()]) {
+            Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+            None => report.error(conf_path, None, message),
+        }
+        config.listen = None;
+    }
+    if let Some(safe_area) = config.auto_fit.safe_area
+        && (safe_area.is_nan() || safe_area <= 0.0 || safe_area > 1.0)
+    {
+        let message = format!("`auto_fit.safe_area` must be between 0 and 1 (got {}), using 0.9", safe_area);
+        match key_offset(&table, &["auto_fit".to_string(), "safe_area".to_string()]) {
+            Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+            None => report.error(conf_path, None, message),
+        }
+        config.auto_fit.safe_area = None;
+    }
+    // 不正なウィンドウは取り除く
+    let windows_offset = key_offset(&table, &["windows".to_string()]);
+    let mut report_window = |message: String| match windows_offset {
+        Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+        None => report.error(conf_path, None, message),
+    };
+    let mut names = vec![MAIN_WINDOW.to_string()];
+    config.windows.retain(|window| {
+        if names.contains(&window.name) {
+            report_window(format!("duplicate window `{}`", window.name));
+            return false;
+        }
+        let size = window.screen_size();
+        if !size.is_finite() || size.x <= 0.0 || size.y <= 0.0 {
+            report_window(format!("window `{}` must have a positive window_width and window_height", window.name));
+            return false;
+        }
+        if let Some(text_size) = window.text_size
+            && (text_size.is_nan() || text_size <= 0.0)
+        {
+            report_window(format!("`text_size` of window `{}` must be positive", window.name));
+            return false;
+        }
+        if let Some(hold) = window.hold
+            && (hold.is_nan() || hold <= 0.0)
+        {
+            report_window(format!("`hold` of window `{}` must be positive", window.name));
+            return false;
+        }
+        if let Some(background) = &window.background
+            && crate::text_spawner::parse_color(background).is_none()
+        {
+            report_window(format!("unknown background color `{}` of window `{}`", background, window.name));
+        }
+        names.push(window.name.clone());
+        true
+    });
+
+    // 不正な領域は取り除く
+    let main_size = config.screen_size();
+    let window_sizes: Vec<_> = config.windows.iter().map(|window| (window.name.clone(), window.screen_size())).collect();
+    let mut names = Vec::new();
+    let regions_offset = key_offset(&table, &["regions".to_string()]);
+    let mut report_region = |message: String| match regions_offset {
+        Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+        None => report.error(conf_path, None, message),
+    };
+    config.regions.retain(|region| {
+        let screen = match region.window.as_deref() {
+            None | Some(MAIN_WINDOW) => main_size,
+            Some(window) => match window_sizes.iter().find(|(name, _)| name == window) {
+                Some((_, size)) => *size,
+                None => {
+                    report_region(format!("window `{}` of region `{}` not found", window, region.name));
+                    return false;
                 }
-            ]);
-            PresetManager { presets: default_presets }
+            },
+        };
+        let invalid = [region.x, region.y, region.width, region.height].iter().any(|v| !v.is_finite())
+            || region.width <= 0.0
+            || region.height <= 0.0;
+        if invalid {
+            report_region(format!("region `{}` must have a positive width and height", region.name));
+            return false;
+        }
+        if region.x < 0.0 || region.y < 0.0 || region.x + region.width > screen.x || region.y + region.height > screen.y {
+            report_region(format!("region `{}` is outside the window ({}x{})", region.name, screen.x, screen.y));
+            return false;
+        }
+        if names.contains(&region.name) {
+            report_region(format!("duplicate region `{}`", region.name));
+            return false;
+        }
+        if let Some(text_size) = region.text_size
+            && (text_size.is_nan() || text_size <= 0.0)
+        {
+            report_region(format!("`text_size` of region `{}` must be positive", region.name));
+            return false;
+        }
+        if let Some(hold) = region.hold
+            && (hold.is_nan() || hold <= 0.0)
+        {
+            report_region(format!("`hold` of region `{}` must be positive", region.name));
+            return false;
+        }
+        if let Some(background) = &region.background
+            && crate::text_spawner::parse_color(background).is_none()
+        {
+            report_region(format!("unknown background color `{}` of region `{}`", background, region.name));
+        }
+        names.push(region.name.clone());
+        true
+    });
+
+    if let Some(effect) = &config.bingo_effect
+        && let Err(e) = crate::effects::parse(effect)
+    {
+        match key_offset(&table, &["bingo_effect".to_string()]) {
+            Some(offset) => report.error_at(conf_path, &file_content, offset, e),
+            None => report.error(conf_path, None, e),
+        }
+        config.bingo_effect = None;
+    }
+    let led_offset = |key: &str| key_offset(&table, &["led".to_string(), key.to_string()]);
+    let mut report_led = |key: &str, message: String| match led_offset(key) {
+        Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+        None => report.error(conf_path, None, message),
+    };
+    if let Some(led) = &config.led
+        && (led.columns == 0 || led.rows == 0 || led.columns > 4096 || led.rows > 4096)
+    {
+        let message = format!("`led` must have 1 to 4096 columns and rows (got {}x{}), LED mode is off", led.columns, led.rows);
+        report_led("columns", message);
+        config.led = None;
+    }
+    if let Some(led) = &mut config.led {
+        if let Some(dot) = led.dot
+            && (dot.is_nan() || dot <= 0.0 || dot > 1.0)
+        {
+            report_led("dot", format!("`led.dot` must be between 0 and 1 (got {}), using 0.8", dot));
+            led.dot = None;
+        }
+        if let Some(glow) = led.glow
+            && (glow.is_nan() || !(0.0..=1.0).contains(&glow))
+        {
+            report_led("glow", format!("`led.glow` must be between 0 and 1 (got {}), using 0.5", glow));
+            led.glow = None;
+        }
+        if let Some(off_color) = &led.off_color
+            && crate::text_spawner::parse_color(off_color).is_none()
+        {
+            report_led("off_color", format!("unknown color `{}` in `led.off_color`", off_color));
+            led.off_color = None;
+        }
+    }
+    // 不正な出力は取り除く
+    let outputs_offset = key_offset(&table, &["outputs".to_string()]);
+    let mut report_output = |message: String| match outputs_offset {
+        Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+        None => report.error(conf_path, None, message),
+    };
+    let screen = config.screen_size();
+    config.outputs.retain(|output| match validate_output(output, screen) {
+        Ok(()) => true,
+        Err(e) => {
+            report_output(e);
+            false
+        }
+    });
+    if let Some(duration) = config.transition.duration
+        && (duration.is_nan() || duration <= 0.0)
+    {
+        let message = format!("`transition.duration` must be positive (got {}), using 0.4", duration);
+        match key_offset(&table, &["transition".to_string(), "duration".to_string()]) {
+            Some(offset) => report.error_at(conf_path, &file_content, offset, message),
+            None => report.error(conf_path, None, message),
         }
+        config.transition.duration = None;
     }
+
+    Some(config)
 }
 
-pub fn unwrap_conf() -> Config {
-    match load_config() {
-        Ok(n) => return n,
-        Err(_e) => {
-            println!("Err: Can't Load Config File!");
-            return Config {
-                text_size: 1080.0,
-                window_width: 1920.0,
-                camera_offset: 0.0,
-            };
-        }
+fn validate_output(output: &OutputConfig, screen: Vec2) -> Result<(), String> {
+    if output.width == 0 || output.height == 0 || output.width > 1024 || output.height > 1024 {
+        return Err(format!("output must have 1 to 1024 pixels in width and height (got {}x{})", output.width, output.height));
+    }
+    if output.protocol == OutputProtocol::Artnet && output.host.is_none() {
+        return Err("artnet output needs a `host`".to_string());
+    }
+    if output.protocol == OutputProtocol::Serial && output.device.is_none() {
+        return Err("serial output needs a `device`".to_string());
+    }
+    if let Some(baud) = output.baud
+        && !crate::serial::supported_baud(baud)
+    {
+        return Err(format!("unsupported serial `baud` {} (use e.g. 115200 or 921600)", baud));
+    }
+    let area = output.area(screen);
+    if !area.min.is_finite() || !area.max.is_finite() || area.width() <= 0.0 || area.height() <= 0.0 {
+        return Err("output `area` must have a positive width and height".to_string());
+    }
+    if area.min.x < 0.0 || area.min.y < 0.0 || area.max.x > screen.x || area.max.y > screen.y {
+        return Err(format!("output `area` is outside the window ({}x{})", screen.x, screen.y));
+    }
+    if let Some(channels) = output.channels
+        && !(3..=512).contains(&channels)
+    {
+        return Err(format!("output `channels` must be between 3 and 512 (got {})", channels));
+    }
+    let range = match output.protocol {
+        OutputProtocol::Artnet => Some((0, 32767)),
+        OutputProtocol::Sacn => Some((1, 63999)),
+        OutputProtocol::Serial => None,
+    };
+    let first_universe = output.first_universe() as usize;
+    let last_universe = first_universe + output.universe_count() - 1;
+    if let Some((first, max)) = range
+        && (first_universe < first || last_universe > max)
+    {
+        return Err(format!(
+            "output universes {}..={} are out of range ({}..={})",
+            first_universe, last_universe, first, max
+        ));
+    }
+    if let Some(order) = &output.color_order
+        && crate::output::color_order(order).is_none()
+    {
+        return Err(format!("unknown output `color_order` `{}` (use e.g. rgb or grb)", order));
+    }
+    let positive = |key: &str, value: Option<f32>| match value {
+        Some(value) if value.is_nan() || value <= 0.0 => Err(format!("output `{}` must be positive (got {})", key, value)),
+        _ => Ok(()),
     };
+    positive("gamma", output.gamma)?;
+    positive("fps", output.fps)?;
+    if let Some(brightness) = output.brightness
+        && (brightness.is_nan() || !(0.0..=1.0).contains(&brightness))
+    {
+        return Err(format!("output `brightness` must be between 0 and 1 (got {})", brightness));
+    }
+    Ok(())
+}
+
+fn report_toml_error(file: &Path, source: &str, e: &toml::de::Error, report: &mut Report) {
+    match e.span() {
+        Some(span) => report.error_at(file, source, span.start, e.message()),
+        None => report.error(file, None, e.message()),
+    }
+}
+
+fn path_segments(path: &serde_ignored::Path) -> Vec<String> {
+    match path {
+        serde_ignored::Path::Root => Vec::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            let mut segments = path_segments(parent);
+            segments.push(index.to_string());
+            segments
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            let mut segments = path_segments(parent);
+            segments.push(key.clone());
+            segments
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => path_segments(parent),
+    }
+}
+
+// キーの位置（見つからない場合は途中までの最も近いキーの位置）
+fn key_offset(root: &DeTable, segments: &[String]) -> Option<usize> {
+    let mut table = root;
+    let mut offset = None;
+    let mut segments = segments.iter();
+    while let Some(segment) = segments.next() {
+        let Some((key, value)) = table.iter().find(|(k, _)| k.get_ref().as_ref() == segment.as_str()) else {
+            break;
+        };
+        offset = Some(key.span().start);
+        let mut value = value.get_ref();
+        // 配列の場合は添字で要素を選ぶ
+        while let DeValue::Array(array) = value {
+            match segments.next().and_then(|i| i.parse::<usize>().ok()).and_then(|i| array.get(i)) {
+                Some(item) => value = item.get_ref(),
+                None => return offset,
+            }
+        }
+        match value {
+            DeValue::Table(next) => table = next,
+            _ => break,
+        }
+    }
+    offset
+}
+
+pub fn unwrap_all_presets(presets_path: &Path, config: &Config, report: &mut Report) -> PresetManager {
+    match load_all_presets(presets_path, config, report) {
+        Ok(presets) => PresetManager::new(presets, config),
+        Err(e) => {
+            report.error(presets_path, None, format!("can't load presets: {}", e));
+            let default_presets = vec![
+                Preset::new("default", vec![TextSource::new("This is a Demo Text", 5.0)]),
+            ];
+            PresetManager::new(default_presets, config)
+        }
+    }
+}
+
+pub fn unwrap_conf(conf_path: &Path, report: &mut Report) -> Config {
+    load_config(conf_path, report).unwrap_or_else(Config::fallback)
 }
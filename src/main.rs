@@ -1,85 +1,272 @@
 use bevy::{
-    color::palettes::tailwind::SLATE_900,  prelude::*, text::TextLayoutInfo
+    app::ScheduleRunnerPlugin, color::palettes::tailwind::SLATE_900,  prelude::*, text::TextLayoutInfo, time::TimeUpdateStrategy,
+    transform::TransformSystems, window::ExitCondition, winit::WinitPlugin
 };
+use std::path::PathBuf;
+use std::time::Duration;
 use bevy_tokio_tasks::TokioTasksPlugin;
+use clap::Parser;
 
 mod bingo;
+mod capture;
+mod cli;
 mod loader;
+mod output;
+mod preset_file;
+mod regions;
+mod render;
+mod serial;
+mod snapshot;
+mod reload;
 mod server;
 mod text;
 mod text_spawner;
 mod countdown;
+mod diagnostics;
+mod effects;
+mod encoding;
+mod export;
+mod fit;
+mod fonts;
+mod images;
+mod led;
+mod markup;
+mod spreadsheet;
+mod template;
+mod transition;
 
-use loader::{Config, TextSource, PresetManager};
+use cli::Cli;
+use loader::{Config, DataPaths, TextSource, PresetManager, ScrollDirection};
+use regions::{RegionId, Regions};
 use bingo::BingoState;
 use countdown::CountdownTimer;
+use diagnostics::Diagnostics;
+pub use fonts::Fonts;
+use reload::ReloadWatcher;
+use template::{TemplateContext, Variables};
+use text_spawner::{EntryStyle, PendingLayout};
 
 fn main() {
-    let preset_manager: PresetManager = loader::unwrap_all_presets();
-    let conf: Config = loader::unwrap_conf();
+    let cli = Cli::parse();
+    if let Some(cli::Command::Convert { input, to, output }) = &cli.command {
+        if let Err(e) = preset_file::convert(input, *to, output.as_deref()) {
+            eprintln!("Err: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // export --raw: 標準出力は書き出す映像だけにする（ログは標準エラー出力に出す）
+    let raw_output = match &cli.command {
+        Some(cli::Command::Export { raw: true, .. }) => match export::take_stdout() {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Err: Can't write to stdout: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    let mut paths = DataPaths::new(&cli);
+    let mut diagnostics = Diagnostics::default();
+    let mut conf: Config = loader::unwrap_conf(&paths.config, &mut diagnostics.config);
+    cli.apply(&mut conf);
+    paths.apply_config(&conf);
+    let preset_manager: PresetManager = loader::unwrap_all_presets(&paths.presets, &conf, &mut diagnostics.presets);
+    preset_manager.check_config(&conf, &paths.config, &mut diagnostics.config);
+    diagnostics.print();
+    conf.print_fixed_size_notes();
+
+    // --check: 検査結果だけを出力して終了
+    if cli.check {
+        if diagnostics.has_errors() {
+            std::process::exit(1);
+        }
+        println!("OK: {} presets", preset_manager.presets.len());
+        return;
+    }
     
-    // デフォルトのプリセット（最初に見つかったもの、またはdefault）を取得
-    let default_preset_name = preset_manager.presets.keys().next().cloned()
-        .unwrap_or_else(|| "default".to_string());
-    let default_texts = preset_manager.presets.get(&default_preset_name)
-        .cloned()
-        .unwrap_or_else(|| vec![TextSource {
-            content: "No presets available".to_string(),
-            duration: 5.0,
-        }]);
+    // 領域ごとにデフォルトのプリセット（指定されたもの、一覧の先頭、またはdefault）を割り当てる
+    let mut regions = Regions::new(&conf, &preset_manager);
+
+    // render・export: 最初の領域に指定されたプリセットを割り当てる（エントリはそれぞれの作業が表示する）
+    if let Some(cli::Command::Render { preset, .. } | cli::Command::Export { preset, .. }) = &cli.command {
+        let Some(texts) = preset_manager.get(preset).map(|preset| preset.texts.clone()) else {
+            eprintln!("Err: Preset '{}' not found", preset);
+            std::process::exit(1);
+        };
+        let region = regions.get_mut(RegionId(0));
+        region.queue.texts = texts;
+        region.queue.current_preset = preset.clone();
+        region.autoplay = false;
+    }
+    let render_job = match &cli.command {
+        Some(cli::Command::Render { preset, index, at, output }) => {
+            let entries = regions.get(RegionId(0)).queue.texts.len();
+            if *index >= entries {
+                eprintln!("Err: Preset '{}' has {} entries", preset, entries);
+                std::process::exit(1);
+            }
+            Some(render::RenderJob::new(preset, *index, *at, output.clone()))
+        }
+        _ => None,
+    };
+    let export_job = match &cli.command {
+        Some(cli::Command::Export { preset, output, fps, hold, .. }) => {
+            if *fps <= 0.0 || *hold < 0.0 {
+                eprintln!("Err: --fps must be positive and --hold must not be negative");
+                std::process::exit(1);
+            }
+            let output = match (raw_output, output) {
+                (Some(file), _) => export::ExportOutput::Raw(file),
+                (None, dir) => {
+                    let dir = dir.clone().unwrap_or_else(|| PathBuf::from("."));
+                    if let Err(e) = std::fs::create_dir_all(&dir) {
+                        eprintln!("Err: Can't create {:?}: {}", dir, e);
+                        std::process::exit(1);
+                    }
+                    export::ExportOutput::Frames(dir)
+                }
+            };
+            // プレビューの書き出し中は LED パネルに送らない
+            conf.outputs.clear();
+            Some(export::ExportJob::new(preset, *fps, *hold, output))
+        }
+        _ => None,
+    };
+    // render・export は実際の時間によらず 1 フレームずつ進め、同じ入力から同じ画像を作る
+    let frame_time = match &cli.command {
+        Some(cli::Command::Render { .. }) => Some(render::FRAME_TIME),
+        Some(cli::Command::Export { fps, .. }) => Some(Duration::from_secs_f64(1.0 / *fps as f64)),
+        _ => None,
+    };
+    let headless = cli.headless();
     
     let mut app = App::new();
-    app.add_plugins(DefaultPlugins)
-        .add_plugins(TokioTasksPlugin::default())
+    // フォントなどをデータディレクトリから読み込めるようにする
+    fonts::register_data_source(&mut app, &paths);
+    if headless {
+        // ウィンドウを開かず、60fps（render・export では時間を決まった量ずつ進めるので待たずに）で描画する
+        let wait = if frame_time.is_some() { Duration::ZERO } else { render::FRAME_TIME };
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                ..default()
+            })
+            .disable::<WinitPlugin>())
+            .add_plugins(ScheduleRunnerPlugin::run_loop(wait));
+    } else {
+        // メインのウィンドウを閉じたら終了する（[[windows]] のウィンドウは閉じても続ける）
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(regions.displays[0].window()),
+                exit_condition: ExitCondition::OnPrimaryClosed,
+                ..default()
+            }));
+    }
+    app.add_plugins(TokioTasksPlugin::default())
         .insert_resource(ClearColor(Color::Srgba(SLATE_900)))
         .insert_resource(preset_manager)
-        .insert_resource(TextQueue {
-            texts: default_texts,
-            current_index: 0,
-            current_preset: default_preset_name,
-        })
+        .insert_resource(regions)
         .insert_resource(conf)
-        .init_resource::<ScrollingState>()
-        .init_resource::<ScrollingSpeed>()
         .init_resource::<Fonts>()
         .init_resource::<BingoState>()
+        .init_resource::<Variables>()
+        .init_resource::<capture::CapturedFrame>()
         .insert_resource(CountdownTimer::new(15.0, countdown::CountdownMode::Decelerated)) // 15秒、減速モード（デフォルト）
+        .insert_resource(ReloadWatcher::new(1.0, &paths)) // 1秒ごとに変更を確認
+        .insert_resource(paths)
+        .insert_resource(diagnostics)
+        .insert_resource(cli)
         .add_systems(Startup, setup)
+        .add_systems(Update, fonts::font_fallback_system)
+        .add_systems(Update, images::image_layout_system.before(text_spawner::measure_text_system))
+        .add_systems(Update, text_spawner::measure_text_system)
+        .add_systems(Update, fit::auto_fit_system)
         .add_systems(Update, text_scroll)
         .add_systems(Update, text_loop)
+        .add_systems(PreUpdate, regions::window_size_system)
+        .add_systems(Update, regions::viewport_system)
+        .add_systems(Update, regions::autoplay_system)
         .add_systems(Update, check_text_completion)
         .add_systems(Update, handle_keyboard_action)
         .add_systems(Update, countdown::countdown_system)
-        .add_systems(Update, countdown::fade_system)
-        .add_systems(Update, countdown::countdown_finished_system);
+        .add_systems(Update, countdown::countdown_finished_system)
+        .add_systems(Update, reload::reload_system)
+        .add_systems(Update, effects::blink_system)
+        .add_systems(Update, effects::pulse_system.after(transition::transition_system))
+        .add_systems(Update, effects::rainbow_system.after(transition::transition_system))
+        .add_systems(Update, effects::shake_system)
+        .add_systems(Update, effects::typewriter_system)
+        .add_systems(Update, template::template_refresh_system)
+        // 大きさ・位置が決まった同じフレームから始める
+        .add_systems(
+            Update,
+            transition::transition_system
+                .after(fonts::font_fallback_system)
+                .after(text_spawner::measure_text_system)
+                .after(fit::auto_fit_system),
+        )
+        .add_systems(Update, diagnostics::error_banner_system)
+        .add_systems(Update, output::output_system.run_if(resource_exists::<output::Outputs>))
+        .add_systems(Update, capture::readback_system.run_if(resource_exists::<capture::Capture>));
     
-    // WebSocketサーバーをセットアップ
-    server::setup_websocket_server(&mut app);
+    // LED の表示（[led]）は描画する直前に位置を揃える
+    led::register(&mut app);
+    app.add_systems(PostUpdate, led::snap_system.after(TransformSystems::Propagate));
+
+    if let Some(frame_time) = frame_time {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(frame_time));
+    }
+    if let Some(job) = render_job {
+        app.insert_resource(job)
+            .add_systems(Update, render::render_start_system)
+            .add_systems(
+                Update,
+                render::render_capture_system
+                    .after(render::render_start_system)
+                    .before(capture::readback_system),
+            );
+    } else if let Some(job) = export_job {
+        app.insert_resource(job)
+            .add_systems(Update, export::export_system.before(capture::readback_system));
+    } else {
+        // WebSocketサーバーをセットアップ
+        server::setup_websocket_server(&mut app);
+    }
     
-    app.run();
+    if app.run().is_error() {
+        std::process::exit(1);
+    }
 }
 
-#[derive(Resource, Default)]
-pub struct Fonts {
-    text_font: TextFont
-}
     
 #[derive(Component)]
-struct TextScroll;
+pub struct TextScroll;
 
-#[derive(Resource)]
+// 領域ごとのプレイリスト
 pub struct TextQueue {
     texts: Vec<TextSource>,
     current_index: usize,
     current_preset: String,
 }
 
-#[derive(Resource, Default)]
+impl TextQueue {
+    // テンプレートの {preset} {index} {total} に使う値
+    pub fn entry_info(&self, index: usize) -> template::EntryInfo {
+        template::EntryInfo {
+            preset: self.current_preset.clone(),
+            index,
+            total: self.texts.len(),
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct ScrollingState {
     is_active: bool,
 }
 
-#[derive(Resource, Default)]
+#[derive(Default)]
 pub struct ScrollingSpeed {
     speed: f32,
 }
@@ -89,175 +276,189 @@ pub struct Showing;
 
 #[derive(Component)]
 pub struct LoopingText {
-    pub original_x: f32,
-    pub text_width: f32,
+    pub start: Vec2,       // 一周したときに戻る位置
+    pub text_length: f32,  // 進行方向のテキストの長さ
     pub loop_speed: f32,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut cmds: Commands,
     config: Res<Config>,
     asset_server: Res<AssetServer>,
+    paths: Res<DataPaths>,
+    regions: Res<Regions>,
+    cli: Res<Cli>,
     mut fonts: ResMut<Fonts>,
-    mut bingo: ResMut<BingoState>
+    mut bingo: ResMut<BingoState>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut led_materials: ResMut<Assets<led::LedMaterial>>,
 ) {
-    let font = asset_server.load("fonts/ipag.ttf");
-    let text_font = TextFont {
-        font: font,
-      font_size: config.text_size,
-        ..default()
-    };
+    *fonts = Fonts::load(&config, &paths, &asset_server);
     let bingo_state = BingoState::new();
-    fonts.text_font = text_font;
     bingo.numbers = bingo_state.numbers;
     bingo.index = bingo_state.index;
-    cmds.spawn((
-        Camera2d,
-        Transform::from_translation(Vec3::new(config.camera_offset, 0.0, 0.0)),
-    ));
+    let led_screen = config.led.as_ref().map(|led| led::create_screen(led, &mut images));
+    // [[outputs]] に送る・スナップショットを撮るため、メインのウィンドウの内容を読み出す
+    let headless = cli.headless();
+    let size = cli.export_size(config.screen_size());
+    let capture = capture::Capture::new(&config, led_screen.as_ref(), headless, size, &mut images);
+    let mirror = capture.as_ref().and_then(capture::Capture::mirror_image);
+    regions::spawn_cameras(&mut cmds, &regions, led_screen.as_ref(), mirror, headless);
+    if let (Some(led), Some(screen)) = (&config.led, led_screen) {
+        if !headless {
+            led::spawn_display(&mut cmds, led, &screen, &mut meshes, &mut led_materials);
+        }
+        cmds.insert_resource(screen);
+    }
+    if let Some(mut capture) = capture {
+        capture::spawn_readback(&mut cmds, &mut capture);
+        cmds.insert_resource(capture);
+    }
+    cmds.insert_resource(output::Outputs::new(&config));
 }
 
+#[allow(clippy::type_complexity)]
 fn text_scroll(
     time: Res<Time>,
-    scrolling_state: Res<ScrollingState>,
-    scrolling_speed: Res<ScrollingSpeed>,
-    mut query: Query<&mut Transform, (With<TextScroll>, Without<LoopingText>)>,
+    regions: Res<Regions>,
+    mut query: Query<(&mut Transform, &ScrollDirection, Option<&RegionId>), (With<TextScroll>, With<Showing>, Without<LoopingText>, Without<PendingLayout>)>,
 ) {
-    if !scrolling_state.is_active {
-        return;
+    for (mut transform, direction, region) in &mut query {
+        // 領域ごとのスクロールの状態・速度
+        let region = regions.get(region.copied().unwrap_or_default());
+        if !region.scrolling.is_active {
+            continue;
+        }
+        transform.translation += (direction.vector() * region.speed.speed * time.delta_secs()).extend(0.0);
     }
+}
 
-    for mut transform in &mut query {
-        transform.translation.x -= scrolling_speed.speed * time.delta_secs()
-    }
+// テキスト全体が進行方向の画面（領域）の端を通り過ぎたか
+fn has_passed(transform: &Transform, direction: ScrollDirection, text_length: f32, screen_size: Vec2) -> bool {
+    let screen_length = direction.along(screen_size);
+    transform.translation.truncate().dot(direction.vector()) > text::calc_text_offset(text_length, screen_length)
 }
 
+#[allow(clippy::type_complexity)]
 fn text_loop(
     time: Res<Time>,
-    config: Res<Config>,
-    mut query: Query<(&mut Transform, &LoopingText), With<Showing>>,
+    regions: Res<Regions>,
+    mut query: Query<(&mut Transform, &LoopingText, &ScrollDirection, Option<&RegionId>), (With<Showing>, Without<PendingLayout>)>,
 ) {
-    for (mut transform, looping_text) in &mut query {
-        // 左に移動
-        transform.translation.x -= looping_text.loop_speed * time.delta_secs();
-        
-        // 通常のスクロールと同じ判定ロジックを使用
-        let text_left_edge = transform.translation.x + (looping_text.text_width + config.window_width) / 2.0 + 5.0;
-        
-        // テキストが完全に画面左端を通り過ぎたかチェック（テキスト全体が画面外に出るまで待つ）
-        if text_left_edge < 0.0 {
-            // 右端から再開
-            transform.translation.x = looping_text.original_x;
+    for (mut transform, looping_text, direction, region) in &mut query {
+        let screen_size = regions.get(region.copied().unwrap_or_default()).size();
+        // 進行方向に移動
+        transform.translation += (direction.vector() * looping_text.loop_speed * time.delta_secs()).extend(0.0);
+
+        // テキストが完全に画面端を通り過ぎたかチェック（テキスト全体が画面外に出るまで待つ）
+        if has_passed(&transform, *direction, looping_text.text_length, screen_size) {
+            // 反対側の端から再開
+            transform.translation = looping_text.start.extend(transform.translation.z);
         }
     }
 }
      
+// キーボードの操作は最初の領域（レイアウトがない場合は画面全体）に対して行う
+#[allow(clippy::too_many_arguments)]
 fn handle_keyboard_action(
     keys: Res<ButtonInput<KeyCode>>,
-    mut scrolling_state: ResMut<ScrollingState>,
-    mut text_queue: ResMut<TextQueue>,
+    mut regions: ResMut<Regions>,
     mut cmds: Commands,
     mut bingo: ResMut<BingoState>,
     mut countdown_timer: ResMut<CountdownTimer>,
     config: Res<Config>,
     fonts: Res<Fonts>,
-    text_query: Query<Entity, With<Showing>>,
-    mut scrolling_speed: ResMut<ScrollingSpeed>,
+    asset_server: Res<AssetServer>,
+    variables: Res<Variables>,
+    paths: Res<DataPaths>,
+    text_query: transition::ShowingQuery,
 ) {
+    let region = regions.get_mut(RegionId(0));
     if keys.just_pressed(KeyCode::Enter) {
-            for entity in text_query.iter() {
-                cmds.entity(entity).despawn();
-	    }
+            transition::dismiss_all(&mut cmds, &text_query, region.id);
 
 	// カウントダウンを停止
 	countdown_timer.stop();
 
-	if text_queue.texts[text_queue.current_index].duration == 0.0 {
-	    text_spawner::spawn_static_text(&mut cmds, &text_queue.texts[text_queue.current_index].content.clone(), fonts.text_font.clone());
-	} else {
-	    text_spawner::spawn_text(
-		&mut cmds,
-		&text_queue.texts[text_queue.current_index].content.clone(),
-		&text_queue.texts[text_queue.current_index].duration,
-		fonts.text_font.clone(),
-		&config,
-		&mut *scrolling_speed,
-        );
+	let index = region.queue.current_index;
+	if regions::spawn_entry(&mut cmds, region, index, &fonts, &config, &paths, &asset_server, &bingo, &variables).is_some() {
+            region.queue.current_index += 1;
 	}
 
-        text_queue.current_index += 1;
-
-	scrolling_state.is_active = true;
+	region.scrolling.is_active = true;
     };
     if keys.just_pressed(KeyCode::KeyB) {
-	for entity in text_query.iter() {
-	    cmds.entity(entity).despawn();
-	}
+	transition::dismiss_all(&mut cmds, &text_query, region.id);
 	
 	// カウントダウンを停止
 	countdown_timer.stop();
 	
-	text_spawner::spawn_static_text(&mut cmds, &bingo.next().unwrap_or(0).to_string(), fonts.text_font.clone());
+	let number = bingo.next().unwrap_or(0);
+	let context = TemplateContext {
+	    entry: region.queue.entry_info(region.queue.current_index),
+	    bingo: &bingo,
+	    variables: &variables,
+	};
+	let style = EntryStyle::bingo(&fonts, &config, region);
+	text_spawner::spawn_static_text(&mut cmds, &number.to_string(), &style, region, &config, &context);
     }
     if keys.just_pressed(KeyCode::KeyC) {
-        for entity in text_query.iter() {
-            cmds.entity(entity).despawn();
-        }
+        transition::dismiss_all(&mut cmds, &text_query, region.id);
         
         // 通常のカウントダウン開始（10秒）
         countdown_timer.mode = countdown::CountdownMode::Normal;
         countdown_timer.initial_seconds = 10.0;
         countdown_timer.timer = bevy::time::Timer::from_seconds(10.0, bevy::time::TimerMode::Once);
+        countdown_timer.region = region.id;
         countdown_timer.start();
     }
     if keys.just_pressed(KeyCode::KeyX) {
-        for entity in text_query.iter() {
-            cmds.entity(entity).despawn();
-        }
+        transition::dismiss_all(&mut cmds, &text_query, region.id);
         
         // 加速カウントダウン開始（15秒）
         countdown_timer.mode = countdown::CountdownMode::Accelerated;
         countdown_timer.initial_seconds = 15.0;
         countdown_timer.timer = bevy::time::Timer::from_seconds(15.0, bevy::time::TimerMode::Once);
+        countdown_timer.region = region.id;
         countdown_timer.start();
     }
     if keys.just_pressed(KeyCode::KeyZ) {
-        for entity in text_query.iter() {
-            cmds.entity(entity).despawn();
-        }
+        transition::dismiss_all(&mut cmds, &text_query, region.id);
         
         // 減速カウントダウン開始（15秒）
         countdown_timer.mode = countdown::CountdownMode::Decelerated;
         countdown_timer.initial_seconds = 15.0;
         countdown_timer.timer = bevy::time::Timer::from_seconds(15.0, bevy::time::TimerMode::Once);
+        countdown_timer.region = region.id;
         countdown_timer.start();
     }	
 }
 
+#[allow(clippy::type_complexity)]
 fn check_text_completion(
     mut cmds: Commands,
-    config: Res<Config>,
-    text_queue: ResMut<TextQueue>,
-    mut scrolling_state: ResMut<ScrollingState>,
-    query: Query<(Entity, &Transform, &TextLayoutInfo), (With<TextScroll>, Without<LoopingText>)>,
+    mut regions: ResMut<Regions>,
+    query: Query<(Entity, &Transform, &TextLayoutInfo, Option<&images::EntryImage>, &ScrollDirection, Option<&RegionId>), (With<TextScroll>, With<Showing>, Without<LoopingText>, Without<PendingLayout>)>,
 ) {
 
-    for (entity, transform, info) in query.iter() {
-        let text_width = info.size.x;
-        let text_left_edge =
-            transform.translation.x + (text_width + config.window_width) / 2.0 + 5.0;
-
-        // テキストが完全に画面左端を通り過ぎたかチェック（テキスト全体が画面外に出るまで待つ）
-        if text_left_edge < 0.0 {
+    for (entity, transform, info, image, direction, region) in query.iter() {
+        let Some(rect) = images::content_rect(info, None, image) else {
+            continue;
+        };
+        let region = regions.get_mut(region.copied().unwrap_or_default());
+        // テキストが完全に画面端を通り過ぎたかチェック（テキスト全体が画面外に出るまで待つ）
+        if has_passed(transform, *direction, direction.along(rect.size()), region.size()) {
             // 現在のテキストエンティティを削除
             cmds.entity(entity).despawn();
 
             // スクロールを停止
-            scrolling_state.is_active = false;
+            region.scrolling.is_active = false;
 	    
             println!(
                 "Next: {} ",
-                text_queue.texts[text_queue.current_index].content
+                region.queue.texts.get(region.queue.current_index).map_or("", |text| text.content.as_str())
             );
             break; // 一度に一つのテキストのみ処理
         }
@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+
+use crate::text_spawner::{named_color, parse_color};
+
+// インライン記法で区切られたテキストの一部分（None の値はエントリの見た目のまま）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+    pub background: Option<Color>,
+    pub scale: f32, // 文字サイズの倍率
+    pub bold: bool,
+}
+
+impl Span {
+    pub fn is_plain(&self) -> bool {
+        self.color.is_none() && self.background.is_none() && self.scale == 1.0 && !self.bold
+    }
+}
+
+enum Tag {
+    Color(Color),
+    Background(Color),
+    Size(f32),
+}
+
+// [red]緊急[/red] [#ff0000]..[/#ff0000] [color=red]..[/color] [bg=navy]..[/bg]
+// [size=1.5]..[/size] **太字** を解釈する
+//
+// [/] は直前のタグを閉じる。解釈できない [..] はそのまま表示し、
+// \[ \* \\ で記号そのものを書ける。
+pub fn parse(s: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut stack: Vec<(&str, Tag)> = Vec::new();
+    let mut bold = false;
+    let mut current = String::new();
+
+    let mut i = 0;
+    while i < s.len() {
+        let tail = &s[i..];
+        if let Some(escaped) = tail.strip_prefix('\\')
+            && let Some(c) = escaped.chars().next().filter(|c| matches!(c, '[' | '*' | '\\'))
+        {
+            current.push(c);
+            i += 1 + c.len_utf8();
+            continue;
+        }
+        if tail.starts_with("**") {
+            flush(&mut spans, &mut current, &stack, bold);
+            bold = !bold;
+            i += 2;
+            continue;
+        }
+        if tail.starts_with('[')
+            && let Some(end) = tail.find(']')
+        {
+            let inner = &tail[1..end];
+            if let Some(name) = inner.strip_prefix('/') {
+                // 閉じタグ: 同じ名前の直近のタグと、その後に開いたタグを閉じる
+                let position = if name.is_empty() {
+                    stack.len().checked_sub(1)
+                } else {
+                    stack.iter().rposition(|(tag_name, _)| *tag_name == name.trim())
+                };
+                if let Some(position) = position {
+                    flush(&mut spans, &mut current, &stack, bold);
+                    stack.truncate(position);
+                    i += end + 1;
+                    continue;
+                }
+            } else if let Some(tag) = parse_tag(inner) {
+                flush(&mut spans, &mut current, &stack, bold);
+                let name = inner.split('=').next().unwrap_or_default().trim();
+                stack.push((name, tag));
+                i += end + 1;
+                continue;
+            }
+        }
+        let c = tail.chars().next().unwrap_or_default();
+        current.push(c);
+        i += c.len_utf8();
+    }
+    flush(&mut spans, &mut current, &stack, bold);
+    spans
+}
+
+fn parse_tag(inner: &str) -> Option<Tag> {
+    match inner.split_once('=') {
+        Some((name, value)) => match name.trim() {
+            "size" => value.trim().parse::<f32>().ok().filter(|size| size.is_finite() && *size > 0.0).map(Tag::Size),
+            "color" => parse_color(value).map(Tag::Color),
+            "bg" | "background" => parse_color(value).map(Tag::Background),
+            _ => None,
+        },
+        // "[abc]" などを色と誤認しないよう、16進数は # 付きのみ
+        None if inner.trim().starts_with('#') => parse_color(inner).map(Tag::Color),
+        None => named_color(inner).map(Tag::Color),
+    }
+}
+
+fn flush(spans: &mut Vec<Span>, current: &mut String, stack: &[(&str, Tag)], bold: bool) {
+    if current.is_empty() {
+        return;
+    }
+    let mut span = Span {
+        text: std::mem::take(current),
+        color: None,
+        background: None,
+        scale: 1.0,
+        bold,
+    };
+    for (_, tag) in stack {
+        match tag {
+            Tag::Color(color) => span.color = Some(*color),
+            Tag::Background(color) => span.background = Some(*color),
+            Tag::Size(size) => span.scale *= size,
+        }
+    }
+    spans.push(span);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::color::palettes::css;
+
+    fn texts(spans: &[Span]) -> Vec<&str> {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn plain_text_is_one_span() {
+        let spans = parse("本日は晴天なり");
+        assert_eq!(texts(&spans), ["本日は晴天なり"]);
+        assert!(spans[0].is_plain());
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn parses_nested_tags() {
+        let spans = parse("[red]緊急[size=2]地震[bg=navy]速報[/bg][/size]です[/red]");
+        assert_eq!(texts(&spans), ["緊急", "地震", "速報", "です"]);
+        let red = Some(Color::Srgba(css::RED));
+        assert!(spans.iter().all(|span| span.color == red));
+        assert_eq!(spans.iter().map(|span| span.scale).collect::<Vec<_>>(), [1.0, 2.0, 2.0, 1.0]);
+        assert_eq!(spans[2].background, Some(Color::Srgba(css::NAVY)));
+        assert_eq!(spans[1].background, None);
+
+        // 倍率は重ねると掛け合わせる
+        let spans = parse("[size=2][size=1.5]大[/][/]");
+        assert_eq!(spans[0].scale, 3.0);
+    }
+
+    #[test]
+    fn closing_tag_closes_inner_tags() {
+        let spans = parse("[color=blue]a[size=2]b[/color]c");
+        assert_eq!(texts(&spans), ["a", "b", "c"]);
+        assert!(spans[2].is_plain());
+    }
+
+    #[test]
+    fn parses_bold() {
+        let spans = parse("**太字**と[#00ff00]**緑**[/#00ff00]");
+        assert_eq!(texts(&spans), ["太字", "と", "緑"]);
+        assert_eq!(spans.iter().map(|span| span.bold).collect::<Vec<_>>(), [true, false, true]);
+        assert_eq!(spans[2].color, Some(Color::Srgba(css::LIME)));
+    }
+
+    #[test]
+    fn keeps_unterminated_and_unknown_markup() {
+        // 閉じられていないタグは最後まで続き、閉じ括弧がなければ文字として表示する
+        let spans = parse("[red]赤のまま");
+        assert_eq!(texts(&spans), ["赤のまま"]);
+        assert_eq!(spans[0].color, Some(Color::Srgba(css::RED)));
+        assert_eq!(texts(&parse("[red 赤")), ["[red 赤"]);
+
+        // 解釈できないタグ・対応しない閉じタグはそのまま表示する
+        assert_eq!(texts(&parse("[abc]x[/abc]")), ["[abc]x[/abc]"]);
+        assert_eq!(texts(&parse("[size=-1]x")), ["[size=-1]x"]);
+
+        // 閉じられていない ** は最後まで太字
+        let spans = parse("a**b");
+        assert_eq!(texts(&spans), ["a", "b"]);
+        assert!(spans[1].bold);
+    }
+
+    #[test]
+    fn escapes_markup() {
+        let spans = parse(r"\[red]\*\*\\");
+        assert_eq!(texts(&spans), [r"[red]**\"]);
+        assert!(spans[0].is_plain());
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+use crate::capture::CapturedFrame;
+use crate::loader::{Config, OutputConfig, OutputProtocol};
+use crate::regions::Regions;
+use crate::serial::{self, SerialOutput};
+
+const ARTNET_PORT: u16 = 6454;
+const SACN_PORT: u16 = 5568;
+const SOURCE_NAME: &str = "NexBoard";
+
+// color_order の文字列を、送る順の RGB の番号にする
+pub fn color_order(order: &str) -> Option<[usize; 3]> {
+    match order.to_ascii_lowercase().as_str() {
+        "rgb" => Some([0, 1, 2]),
+        "rbg" => Some([0, 2, 1]),
+        "grb" => Some([1, 0, 2]),
+        "gbr" => Some([1, 2, 0]),
+        "brg" => Some([2, 0, 1]),
+        "bgr" => Some([2, 1, 0]),
+        _ => None,
+    }
+}
+
+// ガンマ補正と明るさの変換表
+fn color_table(gamma: f32, brightness: f32) -> [u8; 256] {
+    std::array::from_fn(|v| ((v as f32 / 255.0).powf(gamma) * brightness * 255.0).round() as u8)
+}
+
+// 画素を配線の順に並べ、チャンネルの値にする
+fn channels(output: &OutputConfig, pixels: &[[u8; 3]], table: &[u8; 256], order: [usize; 3]) -> Vec<u8> {
+    let (width, height) = (output.width as usize, output.height as usize);
+    let mut channels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        let row = &pixels[y * width..(y + 1) * width];
+        // 折り返し配線では奇数行を逆向きに送る
+        let reversed = output.serpentine && y % 2 == 1;
+        for x in 0..width {
+            let pixel = row[if reversed { width - 1 - x } else { x }];
+            channels.extend(order.map(|c| table[pixel[c] as usize]));
+        }
+    }
+    channels
+}
+
+// Art-Net の ArtDmx パケット
+fn artnet_packet(universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    // データ長は偶数にする
+    let length = data.len() + data.len() % 2;
+    let mut packet = Vec::with_capacity(18 + length);
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&0x5000u16.to_le_bytes()); // OpDmx
+    packet.extend_from_slice(&14u16.to_be_bytes()); // プロトコルのバージョン
+    packet.push(sequence);
+    packet.push(0); // Physical
+    packet.push((universe & 0xff) as u8); // SubNet・Universe
+    packet.push(((universe >> 8) & 0x7f) as u8); // Net
+    packet.extend_from_slice(&(length as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+    packet.resize(18 + length, 0);
+    packet
+}
+
+// sACN（E1.31）のデータパケット
+fn sacn_packet(universe: u16, sequence: u8, cid: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    let total = 126 + data.len();
+    let flags_and_length = |start: usize| (0x7000 | (total - start) as u16).to_be_bytes();
+    let mut packet = Vec::with_capacity(total);
+    // ルートレイヤー
+    packet.extend_from_slice(&0x0010u16.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(b"ASC-E1.17\0\0\0");
+    packet.extend_from_slice(&flags_and_length(16));
+    packet.extend_from_slice(&4u32.to_be_bytes());
+    packet.extend_from_slice(cid);
+    // フレーミングレイヤー
+    packet.extend_from_slice(&flags_and_length(38));
+    packet.extend_from_slice(&2u32.to_be_bytes());
+    let mut source_name = [0u8; 64];
+    source_name[..SOURCE_NAME.len()].copy_from_slice(SOURCE_NAME.as_bytes());
+    packet.extend_from_slice(&source_name);
+    packet.push(100); // 優先度
+    packet.extend_from_slice(&0u16.to_be_bytes()); // 同期アドレス
+    packet.push(sequence);
+    packet.push(0); // オプション
+    packet.extend_from_slice(&universe.to_be_bytes());
+    // DMP レイヤー
+    packet.extend_from_slice(&flags_and_length(115));
+    packet.push(2);
+    packet.push(0xa1);
+    packet.extend_from_slice(&0u16.to_be_bytes()); // 最初のアドレス
+    packet.extend_from_slice(&1u16.to_be_bytes()); // アドレスの増分
+    packet.extend_from_slice(&(data.len() as u16 + 1).to_be_bytes());
+    packet.push(0); // スタートコード
+    packet.extend_from_slice(data);
+    packet
+}
+
+// Art-Net・sACN の送信先
+struct Network {
+    socket: UdpSocket,
+    destination: Option<SocketAddr>, // None は sACN のマルチキャスト（ユニバースごとのアドレス）
+    sequence: u8,
+    cid: [u8; 16],
+    failed: bool, // 送信の失敗を一度だけ表示する
+}
+
+enum Sink {
+    Network(Network),
+    Serial(SerialOutput),
+}
+
+struct Output {
+    config: OutputConfig,
+    sink: Sink,
+    timer: Timer,
+    table: [u8; 256],
+    order: [usize; 3],
+}
+
+impl Output {
+    fn open(config: &OutputConfig) -> std::io::Result<Self> {
+        let sink = match (config.protocol, &config.device) {
+            (OutputProtocol::Serial, Some(device)) => Sink::Serial(SerialOutput::open(device, config.baud.unwrap_or(serial::DEFAULT_BAUD))),
+            _ => Sink::Network(Network::open(config)?),
+        };
+        Ok(Self {
+            config: config.clone(),
+            sink,
+            timer: Timer::from_seconds(1.0 / config.fps.unwrap_or(30.0), TimerMode::Repeating),
+            table: color_table(config.gamma.unwrap_or(2.2), config.brightness.unwrap_or(1.0)),
+            order: config.color_order.as_deref().and_then(color_order).unwrap_or([0, 1, 2]),
+        })
+    }
+
+    // 送信先の表示
+    fn destination(&self) -> String {
+        match &self.sink {
+            Sink::Network(network) => network.destination.map_or("multicast".to_string(), |d| d.to_string()),
+            Sink::Serial(_) => self.config.device.clone().unwrap_or_default(),
+        }
+    }
+
+    fn send(&mut self, channels: &[u8]) {
+        match &mut self.sink {
+            Sink::Network(network) => network.send(&self.config, channels),
+            Sink::Serial(serial) => {
+                let frame = serial::frame(self.config.format, serial.sequence, self.config.width, self.config.height, channels);
+                serial.send(frame);
+            }
+        }
+    }
+}
+
+impl Network {
+    fn open(config: &OutputConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+        let port = config.port.unwrap_or(match config.protocol {
+            OutputProtocol::Sacn => SACN_PORT,
+            OutputProtocol::Artnet | OutputProtocol::Serial => ARTNET_PORT,
+        });
+        let destination = match &config.host {
+            Some(host) => {
+                let destination = (host.as_str(), port).to_socket_addrs()?.next();
+                let destination = destination.ok_or_else(|| std::io::Error::other(format!("can't resolve {}", host)))?;
+                socket.set_broadcast(true)?;
+                Some(destination)
+            }
+            None => None,
+        };
+        Ok(Self {
+            socket,
+            destination,
+            sequence: 0,
+            cid: rand::random(),
+            failed: false,
+        })
+    }
+
+    fn send(&mut self, config: &OutputConfig, channels: &[u8]) {
+        let port = config.port.unwrap_or(SACN_PORT);
+        let chunk = config.pixels_per_universe() * 3;
+        // Art-Net の 0 は「順番なし」なので 1〜255 を繰り返す
+        self.sequence = match (config.protocol, self.sequence) {
+            (OutputProtocol::Artnet, 255) => 1,
+            (_, sequence) => sequence.wrapping_add(1),
+        };
+        for (i, data) in channels.chunks(chunk).enumerate() {
+            let universe = config.first_universe() + i as u16;
+            let packet = match config.protocol {
+                OutputProtocol::Sacn => sacn_packet(universe, self.sequence, &self.cid, data),
+                OutputProtocol::Artnet | OutputProtocol::Serial => artnet_packet(universe, self.sequence, data),
+            };
+            // sACN で送信先がなければ 239.255.(ユニバースの上位).(下位) に送る
+            let multicast = Ipv4Addr::new(239, 255, (universe >> 8) as u8, universe as u8);
+            let destination = self.destination.unwrap_or(SocketAddr::from((multicast, port)));
+            match self.socket.send_to(&packet, destination) {
+                Ok(_) => self.failed = false,
+                Err(e) if !self.failed => {
+                    println!("Err: Can't send output to {}: {}", destination, e);
+                    self.failed = true;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+// [[outputs]] の送信先（起動時に開く）
+#[derive(Resource, Default)]
+pub struct Outputs {
+    outputs: Vec<Output>,
+}
+
+impl Outputs {
+    pub fn new(config: &Config) -> Self {
+        let mut outputs = Vec::new();
+        for output in &config.outputs {
+            match Output::open(output) {
+                Ok(opened) => {
+                    println!("Output: {:?} {}x{} to {}", output.protocol, output.width, output.height, opened.destination());
+                    outputs.push(opened);
+                }
+                Err(e) => println!("Err: Can't open {:?} output: {}", output.protocol, e),
+            }
+        }
+        Self { outputs }
+    }
+}
+
+// 読み出した画面を縮小して送る
+pub fn output_system(
+    time: Res<Time>,
+    regions: Res<Regions>,
+    frame: Res<CapturedFrame>,
+    mut outputs: ResMut<Outputs>,
+) {
+    if frame.count == 0 {
+        return;
+    }
+    let screen = regions.displays[0].size;
+    for output in &mut outputs.outputs {
+        if !output.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let config = &output.config;
+        let pixels = frame.sample(screen, config.area(screen), config.width, config.height);
+        let channels = channels(config, &pixels, &output.table, output.order);
+        output.send(&channels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn output_config(toml: &str) -> OutputConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    // 127.0.0.1 で受け取るソケットと、そこに送る設定
+    fn listener(protocol: &str, extra: &str) -> (UdpSocket, OutputConfig) {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let config = output_config(&format!(
+            "protocol = \"{}\"\nhost = \"127.0.0.1\"\nport = {}\n{}",
+            protocol, port, extra
+        ));
+        (socket, config)
+    }
+
+    fn receive(socket: &UdpSocket) -> Vec<u8> {
+        let mut buffer = [0u8; 1024];
+        let (length, _) = socket.recv_from(&mut buffer).unwrap();
+        buffer[..length].to_vec()
+    }
+
+    const IDENTITY: [u8; 256] = {
+        let mut table = [0u8; 256];
+        let mut i = 0;
+        while i < 256 {
+            table[i] = i as u8;
+            i += 1;
+        }
+        table
+    };
+
+    #[test]
+    fn sends_artnet_to_local_listener() {
+        let (socket, config) = listener("artnet", "width = 3\nheight = 1\nuniverse = 0x123");
+        let mut network = Network::open(&config).unwrap();
+        network.send(&config, &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let packet = receive(&socket);
+        assert_eq!(&packet[..8], b"Art-Net\0");
+        assert_eq!(&packet[8..10], &[0x00, 0x50]); // OpDmx（リトルエンディアン）
+        assert_eq!(&packet[10..12], &[0, 14]);
+        assert_eq!(packet[12], 1); // 最初の番号は 1
+        assert_eq!(packet[14], 0x23); // SubNet・Universe
+        assert_eq!(packet[15], 0x01); // Net
+        // 9 チャンネルは偶数の 10 にして送る
+        assert_eq!(&packet[16..18], &[0, 10]);
+        assert_eq!(&packet[18..], &[1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+    }
+
+    #[test]
+    fn artnet_sequence_skips_zero() {
+        let (socket, config) = listener("artnet", "width = 1\nheight = 1");
+        let mut network = Network::open(&config).unwrap();
+        network.sequence = 254;
+        for expected in [255, 1, 2] {
+            network.send(&config, &[0, 0, 0]);
+            assert_eq!(receive(&socket)[12], expected);
+        }
+    }
+
+    #[test]
+    fn splits_universes() {
+        let (socket, config) = listener("artnet", "width = 5\nheight = 1\nuniverse = 7\nchannels = 6");
+        assert_eq!(config.universe_count(), 3);
+        let mut network = Network::open(&config).unwrap();
+        let data: Vec<u8> = (1..=15).collect();
+        network.send(&config, &data);
+
+        let packets: Vec<_> = (0..3).map(|_| receive(&socket)).collect();
+        assert_eq!(packets.iter().map(|packet| packet[14]).collect::<Vec<_>>(), [7, 8, 9]);
+        assert_eq!(&packets[0][18..], &data[..6]);
+        assert_eq!(&packets[1][18..], &data[6..12]);
+        assert_eq!(&packets[2][18..], &[13, 14, 15, 0]);
+    }
+
+    #[test]
+    fn sends_sacn_to_local_listener() {
+        let (socket, config) = listener("sacn", "width = 10\nheight = 1");
+        let mut network = Network::open(&config).unwrap();
+        let data: Vec<u8> = (0..30).collect();
+        network.send(&config, &data);
+
+        let packet = receive(&socket);
+        assert_eq!(packet.len(), 126 + 30);
+        assert_eq!(&packet[4..16], b"ASC-E1.17\0\0\0");
+        assert_eq!(&packet[22..38], &network.cid);
+        assert_eq!(&packet[113..115], &1u16.to_be_bytes()); // 最初のユニバースは 1
+        assert_eq!(&packet[126..], &data[..]);
+    }
+
+    #[test]
+    fn sacn_flags_and_lengths() {
+        let data = [0xaa; 30];
+        let packet = sacn_packet(1, 5, &[0; 16], &data);
+        let field = |offset: usize| u16::from_be_bytes([packet[offset], packet[offset + 1]]);
+        assert_eq!(packet.len(), 156);
+        // 上位 4 ビットはフラグ 0x7、残りはその位置からパケットの最後までの長さ
+        assert_eq!(field(16), 0x7000 | 140);
+        assert_eq!(field(38), 0x7000 | 118);
+        assert_eq!(field(115), 0x7000 | 41);
+        assert_eq!(packet[111], 5); // 番号
+        // プロパティの数はスタートコードの分だけ多い
+        assert_eq!(field(123), 31);
+        assert_eq!(packet[125], 0);
+    }
+
+    #[test]
+    fn orders_channels() {
+        let pixels = [[1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12], [13, 14, 15], [16, 17, 18]];
+        let config = output_config("protocol = \"artnet\"\nwidth = 3\nheight = 2");
+        assert_eq!(channels(&config, &pixels, &IDENTITY, [0, 1, 2]), (1..=18).collect::<Vec<u8>>());
+
+        // 折り返し配線は 2 行目を右から送る
+        let config = output_config("protocol = \"artnet\"\nwidth = 3\nheight = 2\nserpentine = true");
+        assert_eq!(
+            channels(&config, &pixels, &IDENTITY, color_order("grb").unwrap()),
+            [2, 1, 3, 5, 4, 6, 8, 7, 9, 17, 16, 18, 14, 13, 15, 11, 10, 12]
+        );
+    }
+
+    #[test]
+    fn applies_gamma_and_brightness() {
+        let table = color_table(1.0, 1.0);
+        assert_eq!(table, IDENTITY);
+        let table = color_table(2.2, 0.5);
+        assert_eq!((table[0], table[255]), (0, 128));
+        assert!(table[128] < 64);
+    }
+}
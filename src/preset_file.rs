@@ -0,0 +1,215 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::path::Path;
+
+use crate::diagnostics::Report;
+use crate::loader::{PresetMeta, TextSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PresetFormat {
+    #[value(skip)]
+    Csv,
+    Toml,
+    Json,
+    Yaml,
+    #[value(skip)]
+    Spreadsheet,
+}
+
+impl PresetFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "xlsx" | "xlsm" | "xls" | "ods" => Some(Self::Spreadsheet),
+            _ => None,
+        }
+    }
+}
+
+// TOML/JSON/YAML 共通のプリセットファイルの形式
+//
+// ```toml
+// display_name = "朝の案内"
+// description = "開場〜12時"
+//
+// [defaults]          # 各項目で省略された値（任意）
+// color = "white"
+//
+// [[items]]
+// content = """
+// 複数行の
+// お知らせ"""
+// duration = 10
+// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PresetFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<Map<String, Value>>,
+    pub items: Vec<TextSource>,
+}
+
+const TOP_LEVEL_KEYS: &[&str] = &["display_name", "description", "defaults", "items"];
+
+pub fn load(path: &Path, format: PresetFormat, report: &mut Report) -> Result<(PresetMeta, Vec<TextSource>), Box<dyn Error>> {
+    let (file_content, encoding) = crate::encoding::read_text(path)?;
+    println!("Reading {:?} as {}", path, encoding);
+    let Some(root) = parse(path, format, &file_content, report) else {
+        return Ok((PresetMeta::default(), Vec::new()));
+    };
+    let Value::Object(mut root) = root else {
+        report.error(path, None, "preset file must be a table/object");
+        return Ok((PresetMeta::default(), Vec::new()));
+    };
+
+    for key in root.keys() {
+        if !TOP_LEVEL_KEYS.contains(&key.as_str()) {
+            report.warning(path, None, format!("unknown key `{}`", key));
+        }
+    }
+    let meta = PresetMeta {
+        display_name: take_string(&mut root, "display_name", path, report),
+        description: take_string(&mut root, "description", path, report),
+    };
+    let defaults = match root.remove("defaults") {
+        None => Map::new(),
+        Some(Value::Object(defaults)) => defaults,
+        Some(_) => {
+            report.error(path, None, "`defaults` must be a table/object");
+            Map::new()
+        }
+    };
+    let items = match root.remove("items") {
+        Some(Value::Array(items)) => items,
+        Some(_) => {
+            report.error(path, None, "`items` must be an array");
+            return Ok((meta, Vec::new()));
+        }
+        None => {
+            report.error(path, None, "missing key `items`");
+            return Ok((meta, Vec::new()));
+        }
+    };
+
+    let mut result = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let Value::Object(mut item) = item else {
+            report.error(path, None, format!("items[{}]: must be a table/object", index));
+            continue;
+        };
+        // defaults の値で省略された項目を補う
+        for (key, value) in &defaults {
+            item.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        let mut unknown_keys = Vec::new();
+        let text_source: Result<TextSource, _> = serde_ignored::deserialize(Value::Object(item), |key| {
+            unknown_keys.push(key.to_string());
+        });
+        for key in unknown_keys {
+            report.warning(path, None, format!("items[{}]: unknown key `{}`", index, key));
+        }
+        match text_source {
+            Ok(text_source) => {
+                let mut item_report = Report::default();
+                let ok = crate::loader::validate_text_source(&text_source, path, None, &mut item_report);
+                for mut diagnostic in item_report.items {
+                    diagnostic.message = format!("items[{}]: {}", index, diagnostic.message);
+                    report.items.push(diagnostic);
+                }
+                if ok {
+                    result.push(text_source);
+                }
+            }
+            Err(e) => report.error(path, None, format!("items[{}]: {}", index, e)),
+        }
+    }
+    Ok((meta, result))
+}
+
+fn parse(path: &Path, format: PresetFormat, file_content: &str, report: &mut Report) -> Option<Value> {
+    match format {
+        PresetFormat::Toml => match toml::from_str::<Value>(file_content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                match e.span() {
+                    Some(span) => report.error_at(path, file_content, span.start, e.message()),
+                    None => report.error(path, None, e.message()),
+                }
+                None
+            }
+        },
+        PresetFormat::Json => match serde_json::from_str::<Value>(file_content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                report.error_at_position(path, e.line(), e.column(), e.to_string());
+                None
+            }
+        },
+        PresetFormat::Yaml => match serde_yaml::from_str::<Value>(file_content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                match e.location() {
+                    Some(location) => report.error_at_position(path, location.line(), location.column(), e.to_string()),
+                    None => report.error(path, None, e.to_string()),
+                }
+                None
+            }
+        },
+        PresetFormat::Csv | PresetFormat::Spreadsheet => unreachable!("CSV and spreadsheet presets are loaded by loader"),
+    }
+}
+
+fn take_string(root: &mut Map<String, Value>, key: &str, path: &Path, report: &mut Report) -> Option<String> {
+    match root.remove(key)? {
+        Value::String(s) => Some(s),
+        _ => {
+            report.warning(path, None, format!("`{}` must be a string", key));
+            None
+        }
+    }
+}
+
+// プリセットを TOML/JSON/YAML の文字列に変換
+pub fn to_string(preset_file: &PresetFile, format: PresetFormat) -> Result<String, Box<dyn Error>> {
+    let s = match format {
+        PresetFormat::Toml => toml::to_string_pretty(preset_file)?,
+        PresetFormat::Json => serde_json::to_string_pretty(preset_file)? + "\n",
+        PresetFormat::Yaml => serde_yaml::to_string(preset_file)?,
+        PresetFormat::Csv | PresetFormat::Spreadsheet => return Err("conversion to CSV or spreadsheet is not supported".into()),
+    };
+    Ok(s)
+}
+
+// convert サブコマンド: エラーのある行を落とさないよう、問題があれば変換しない
+pub fn convert(input: &Path, to: PresetFormat, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let mut report = Report::default();
+    let (meta, items) = crate::loader::load_preset_file(input, &Default::default(), &mut report)?;
+    for diagnostic in &report.items {
+        eprintln!("{}", diagnostic);
+    }
+    if report.has_errors() {
+        return Err(format!("{} has errors, not converted", input.display()).into());
+    }
+
+    let preset_file = PresetFile {
+        display_name: meta.display_name,
+        description: meta.description,
+        defaults: None,
+        items,
+    };
+    let s = to_string(&preset_file, to)?;
+    match output {
+        Some(output) => std::fs::write(output, s)?,
+        None => print!("{}", s),
+    }
+    Ok(())
+}
@@ -0,0 +1,643 @@
+use bevy::{
+    camera::{visibility::RenderLayers, RenderTarget, ScalingMode, Viewport},
+    prelude::*,
+    sprite::Anchor,
+    window::{MonitorSelection, PrimaryWindow, WindowMode, WindowPosition, WindowRef},
+};
+
+use crate::bingo::BingoState;
+use crate::led::LedScreen;
+use crate::loader::{Config, DataPaths, PresetManager, TextSource, MAIN_WINDOW};
+use crate::template::{TemplateContext, Variables};
+use crate::text_spawner::{self, EntryStyle};
+use crate::transition::{self, ShowingQuery};
+use crate::{Fonts, LoopingText, ScrollingSpeed, ScrollingState, Showing, TextQueue, TextScroll};
+
+// autoplay で静止・ループするエントリを表示する秒数（hold の指定がない場合）
+const DEFAULT_HOLD: f32 = 5.0;
+
+// 表示中のエンティティがどの領域のものか（ない場合は最初の領域）
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegionId(pub usize);
+
+impl RegionId {
+    // 領域ごとのカメラだけに表示する
+    pub fn layer(self) -> RenderLayers {
+        RenderLayers::layer(self.0)
+    }
+}
+
+// ウィンドウ（メインのウィンドウと [[windows]]）
+pub struct Display {
+    pub name: String,
+    pub size: Vec2, // 見えている範囲（window_width・window_height の指定があればその大きさ）
+    pub camera_offset: f32,
+    pub monitor: Option<usize>,
+    pub layout: bool,        // [[regions]] で分割されている
+    pub follow_window: bool, // window_width の指定も分割もなく、ウィンドウの大きさに合わせる
+}
+
+impl Display {
+    pub fn window(&self) -> Window {
+        let mut window = Window::default();
+        if self.name != MAIN_WINDOW {
+            window.title = self.name.clone();
+        }
+        // モニターを指定した場合はそのモニターいっぱいに表示する
+        if let Some(monitor) = self.monitor {
+            window.position = WindowPosition::Centered(MonitorSelection::Index(monitor));
+            window.mode = WindowMode::BorderlessFullscreen(MonitorSelection::Index(monitor));
+        }
+        window
+    }
+}
+
+// 画面の一部（[[regions]]）、またはレイアウトがない場合はウィンドウ全体
+pub struct Region {
+    pub id: RegionId,
+    pub name: String,
+    pub window: usize, // Regions::displays の番号（0 はメインのウィンドウ）
+    pub rect: Rect, // 画面の左上を原点とした位置・大きさ（px）
+    pub text_size: Option<f32>,
+    pub background: Option<Color>,
+    pub autoplay: bool,
+    pub hold: f32,
+    hold_until: Option<f64>,     // 表示中の静止・ループするエントリを消す時刻
+    retry_at: Option<f64>,       // どのエントリも表示できなかった場合に次に試す時刻
+    pub queue: TextQueue,
+    pub scrolling: ScrollingState,
+    pub speed: ScrollingSpeed,
+}
+
+impl Region {
+    pub fn size(&self) -> Vec2 {
+        self.rect.size()
+    }
+
+    // 自動調整で静止テキストを収める領域の大きさ
+    pub fn safe_area(&self, config: &Config) -> Vec2 {
+        self.size() * config.auto_fit.safe_area.unwrap_or(0.9)
+    }
+}
+
+#[derive(Resource)]
+pub struct Regions {
+    pub regions: Vec<Region>, // ウィンドウの順に並べる（最初はメインのウィンドウの領域）
+    pub displays: Vec<Display>,
+}
+
+impl Regions {
+    pub fn new(config: &Config, preset_manager: &PresetManager) -> Self {
+        let mut displays = vec![Display {
+            name: MAIN_WINDOW.to_string(),
+            size: config.screen_size(),
+            camera_offset: config.camera_offset,
+            monitor: config.monitor,
+            layout: false,
+            // [[outputs]] に送る画像・範囲は起動時の大きさで決まるので、出力がある場合も大きさに合わせない
+            follow_window: config.window_width.is_none() && config.outputs.is_empty(),
+        }];
+        displays.extend(config.windows.iter().map(|window| Display {
+            name: window.name.clone(),
+            size: window.screen_size(),
+            camera_offset: window.camera_offset,
+            monitor: window.monitor,
+            layout: false,
+            follow_window: window.window_width.is_none(),
+        }));
+
+        let mut regions = Vec::new();
+        for (index, display) in displays.iter_mut().enumerate() {
+            let region_configs: Vec<_> = config
+                .regions
+                .iter()
+                .filter(|region| region.window.as_deref().unwrap_or(MAIN_WINDOW) == display.name)
+                .collect();
+            display.layout = !region_configs.is_empty();
+            // 領域の位置は window_width・window_height の画面で決めるので、分割したウィンドウは大きさに合わせない
+            display.follow_window &= !display.layout;
+
+            // 分割されていないウィンドウは全体で一つの領域（[[windows]] の preset などを使う）
+            if region_configs.is_empty() {
+                let screen = Rect::from_corners(Vec2::ZERO, display.size);
+                let window_config = index.checked_sub(1).and_then(|i| config.windows.get(i));
+                let preset = window_config.and_then(|window| window.preset.as_deref());
+                let queue = queue_for(preset, config, preset_manager);
+                let mut region = Region::new(RegionId(regions.len()), &display.name, index, screen, queue);
+                if let Some(window) = window_config {
+                    region.text_size = window.text_size;
+                    region.background = window.background.as_deref().and_then(text_spawner::parse_color);
+                    region.autoplay = window.autoplay;
+                    region.hold = window.hold.unwrap_or(DEFAULT_HOLD);
+                }
+                regions.push(region);
+                continue;
+            }
+
+            for region_config in region_configs {
+                let rect = Rect::new(
+                    region_config.x,
+                    region_config.y,
+                    region_config.x + region_config.width,
+                    region_config.y + region_config.height,
+                );
+                let queue = queue_for(region_config.preset.as_deref(), config, preset_manager);
+                let mut region = Region::new(RegionId(regions.len()), &region_config.name, index, rect, queue);
+                region.text_size = region_config.text_size;
+                region.background = region_config.background.as_deref().and_then(text_spawner::parse_color);
+                region.autoplay = region_config.autoplay;
+                region.hold = region_config.hold.unwrap_or(DEFAULT_HOLD);
+                regions.push(region);
+            }
+        }
+        Self { regions, displays }
+    }
+
+    // ウィンドウ・領域の名前で探す（どちらも None の場合は最初の領域、ウィンドウだけの場合はその最初の領域）
+    pub fn find(&self, window: Option<&str>, name: Option<&str>) -> Option<RegionId> {
+        let window = match window {
+            Some(window) => Some(self.displays.iter().position(|display| display.name == window)?),
+            None => None,
+        };
+        let mut candidates = self.regions.iter().filter(|region| window.is_none_or(|window| region.window == window));
+        let region = match name {
+            Some(name) => candidates.find(|region| region.name == name),
+            None => candidates.next(),
+        };
+        region.map(|region| region.id)
+    }
+
+    pub fn display(&self, id: RegionId) -> &Display {
+        &self.displays[self.get(id).window]
+    }
+
+    pub fn get(&self, id: RegionId) -> &Region {
+        self.regions.get(id.0).unwrap_or(&self.regions[0])
+    }
+
+    pub fn get_mut(&mut self, id: RegionId) -> &mut Region {
+        let index = if id.0 < self.regions.len() { id.0 } else { 0 };
+        &mut self.regions[index]
+    }
+
+    // ウィンドウに合わせる画面の大きさを変える（分割されていないので領域は画面全体）
+    fn resize(&mut self, index: usize, size: Vec2) {
+        self.displays[index].size = size;
+        for region in self.regions.iter_mut().filter(|region| region.window == index) {
+            region.rect = Rect::from_corners(Vec2::ZERO, size);
+        }
+    }
+}
+
+impl Region {
+    fn new(id: RegionId, name: &str, window: usize, rect: Rect, queue: TextQueue) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            window,
+            rect,
+            text_size: None,
+            background: None,
+            autoplay: false,
+            hold: DEFAULT_HOLD,
+            hold_until: None,
+            retry_at: None,
+            queue,
+            scrolling: ScrollingState::default(),
+            speed: ScrollingSpeed::default(),
+        }
+    }
+}
+
+// 指定されたプリセット、なければデフォルトのプリセット（一覧の先頭、またはdefault）
+fn queue_for(preset: Option<&str>, config: &Config, preset_manager: &PresetManager) -> TextQueue {
+    let preset = match preset {
+        Some(name) => preset_manager.get(name).or_else(|| preset_manager.default_preset(config)),
+        None => preset_manager.default_preset(config),
+    };
+    let (current_preset, texts) = match preset {
+        Some(preset) => (preset.name.clone(), preset.texts.clone()),
+        None => ("default".to_string(), vec![TextSource::new("No presets available", 5.0)]),
+    };
+    TextQueue {
+        texts,
+        current_index: 0,
+        current_preset,
+    }
+}
+
+#[derive(Component)]
+pub struct RegionCamera(pub RegionId);
+
+// ウィンドウごとにカメラを作る（メインのウィンドウ以外のウィンドウもここで作る）
+//
+// LED の表示ではメインのウィンドウの内容は画像に描画する。mirror を指定すると、
+// 出力（[[outputs]]）に送るためメインのウィンドウと同じ内容をその画像にも描画する。
+pub fn spawn_cameras(cmds: &mut Commands, regions: &Regions, led: Option<&LedScreen>, mirror: Option<&Handle<Image>>, headless: bool) {
+    for (index, display) in regions.displays.iter().enumerate() {
+        match (index, led) {
+            (0, Some(screen)) => spawn_display_cameras(cmds, regions, index, screen.0.clone().into(), true, false),
+            // ウィンドウを開かない場合はメインのウィンドウの内容を画像（mirror）にだけ描画する
+            _ if headless => {}
+            (0, None) => spawn_display_cameras(cmds, regions, index, RenderTarget::Window(WindowRef::Primary), false, true),
+            _ => {
+                let window = cmds.spawn(display.window()).id();
+                spawn_display_cameras(cmds, regions, index, RenderTarget::Window(WindowRef::Entity(window)), false, false);
+            }
+        }
+    }
+    if let Some(image) = mirror {
+        spawn_display_cameras(cmds, regions, 0, image.clone().into(), true, headless);
+    }
+    spawn_backgrounds(cmds, regions);
+}
+
+// 分割されていない場合はカメラ一つ、分割されている場合は領域ごとのカメラ
+// （fixed: 画像の大きさによらず画面全体を写す、ui: エラーバナーなどの UI を重ねる）
+fn spawn_display_cameras(cmds: &mut Commands, regions: &Regions, index: usize, target: RenderTarget, fixed: bool, ui: bool) {
+    let display = &regions.displays[index];
+    let offset = Transform::from_translation(Vec3::new(display.camera_offset, 0.0, 0.0));
+    let mut display_regions = regions.regions.iter().filter(|region| region.window == index);
+
+    if !display.layout {
+        let Some(region) = display_regions.next() else {
+            return;
+        };
+        let mut camera = cmds.spawn((
+            Camera2d,
+            Camera {
+                target,
+                clear_color: region.background.map_or(ClearColorConfig::Default, ClearColorConfig::Custom),
+                ..default()
+            },
+            offset,
+            region.id.layer(),
+            RegionCamera(region.id),
+        ));
+        if fixed {
+            camera.insert(Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: display.size.x,
+                    height: display.size.y,
+                },
+                ..OrthographicProjection::default_2d()
+            }));
+        }
+        if ui {
+            camera.insert(IsDefaultUiCamera);
+        }
+        return;
+    }
+
+    // 領域の外側を背景色で塗る
+    cmds.spawn((
+        Camera2d,
+        Camera {
+            order: -1,
+            target: target.clone(),
+            ..default()
+        },
+        RenderLayers::none(),
+    ));
+    let mut order = 0;
+    for region in display_regions {
+        cmds.spawn((
+            Camera2d,
+            Camera {
+                order,
+                target: target.clone(),
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            // 領域の大きさを px 単位で写す（ウィンドウの大きさによらない）
+            Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: region.size().x,
+                    height: region.size().y,
+                },
+                ..OrthographicProjection::default_2d()
+            }),
+            offset,
+            region.id.layer(),
+            RegionCamera(region.id),
+        ));
+        order += 1;
+    }
+    // エラーバナーなどの UI はウィンドウ全体に重ねる
+    if ui {
+        cmds.spawn((
+            Camera2d,
+            Camera {
+                order,
+                target,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            RenderLayers::none(),
+            IsDefaultUiCamera,
+        ));
+    }
+}
+
+// 領域の背景（[[regions]] の background）
+fn spawn_backgrounds(cmds: &mut Commands, regions: &Regions) {
+    for region in &regions.regions {
+        let display = &regions.displays[region.window];
+        if let (true, Some(background)) = (display.layout, region.background) {
+            cmds.spawn((
+                Sprite::from_color(background, region.size()),
+                Transform::from_xyz(display.camera_offset, 0.0, -100.0),
+                region.id.layer(),
+            ));
+        }
+    }
+}
+
+// window_width の指定がない画面の大きさを、ウィンドウの大きさとカメラの投影から求める（ウィンドウの大きさが変わったら合わせ直す）
+#[allow(clippy::type_complexity)]
+pub fn window_size_system(
+    mut regions: ResMut<Regions>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+    mut cameras: Query<(&RegionCamera, &Camera, &mut Projection)>,
+    mut texts: Query<(&mut Transform, &Anchor, Option<&RegionId>), (With<Showing>, Without<TextScroll>, Without<LoopingText>)>,
+) {
+    let mut resized = Vec::new(); // 大きさが変わった画面の番号と元の大きさ
+    for (region_camera, camera, projection) in &cameras {
+        let index = regions.get(region_camera.0).window;
+        if !regions.displays[index].follow_window || resized.iter().any(|(resized, _)| *resized == index) {
+            continue;
+        }
+        let window = match &camera.target {
+            RenderTarget::Window(WindowRef::Primary) => primary_window.single().ok(),
+            RenderTarget::Window(WindowRef::Entity(entity)) => windows.get(*entity).ok(),
+            _ => None,
+        };
+        // WindowSize の投影では 1 論理ピクセルが 1 単位（scale 倍の範囲が見える）
+        let (Some(window), Projection::Orthographic(projection)) = (window, projection) else {
+            continue;
+        };
+        if !matches!(projection.scaling_mode, ScalingMode::WindowSize) {
+            continue;
+        }
+        let size = window.size() * projection.scale;
+        let previous = regions.displays[index].size;
+        if size.x <= 0.0 || size.y <= 0.0 || size == previous {
+            continue;
+        }
+        regions.resize(index, size);
+        resized.push((index, previous));
+    }
+    if resized.is_empty() {
+        return;
+    }
+
+    // 同じ画面を画像に描画するカメラ（[[outputs]] 用）も見える範囲を合わせる
+    for (region_camera, _, mut projection) in &mut cameras {
+        let index = regions.get(region_camera.0).window;
+        if !resized.iter().any(|(resized, _)| *resized == index) {
+            continue;
+        }
+        let size = regions.displays[index].size;
+        if let Projection::Orthographic(projection) = &mut *projection
+            && let ScalingMode::Fixed { width, height } = &mut projection.scaling_mode
+        {
+            *width = size.x;
+            *height = size.y;
+        }
+    }
+
+    // 左寄せ・右寄せの静止テキストを新しい画面の端に揃える
+    for (mut transform, anchor, region) in &mut texts {
+        let region = regions.get(region.copied().unwrap_or_default());
+        let Some((_, previous)) = resized.iter().find(|(index, _)| region.window == *index) else {
+            continue;
+        };
+        let shift = (region.size().x - previous.x) / 2.0;
+        if *anchor == Anchor::CENTER_LEFT {
+            transform.translation.x -= shift;
+        } else if *anchor == Anchor::CENTER_RIGHT {
+            transform.translation.x += shift;
+        }
+    }
+}
+
+// ウィンドウの実際の大きさに合わせて領域ごとの描画範囲を決める
+pub fn viewport_system(
+    regions: Res<Regions>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+    images: Res<Assets<Image>>,
+    mut cameras: Query<(&RegionCamera, &mut Camera)>,
+) {
+    for (region_camera, mut camera) in &mut cameras {
+        let display = regions.display(region_camera.0);
+        if !display.layout {
+            continue;
+        }
+        let window = match &camera.target {
+            RenderTarget::Window(WindowRef::Primary) => primary_window.single().ok(),
+            RenderTarget::Window(WindowRef::Entity(entity)) => Some(*entity),
+            _ => None,
+        };
+        let window_size = match &camera.target {
+            RenderTarget::Image(image) => images.get(&image.handle).map(Image::size),
+            _ => window.and_then(|window| windows.get(window).ok()).map(Window::physical_size),
+        };
+        let Some(window_size) = window_size else {
+            continue;
+        };
+        if window_size.x == 0 || window_size.y == 0 {
+            continue;
+        }
+        let scale = window_size.as_vec2() / display.size;
+        let rect = regions.get(region_camera.0).rect;
+        let position = (rect.min * scale).as_uvec2().min(window_size - UVec2::ONE);
+        let size = (rect.size() * scale).as_uvec2().max(UVec2::ONE).min(window_size - position);
+        let viewport = Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        };
+        if camera.viewport.as_ref().is_none_or(|current| {
+            current.physical_position != viewport.physical_position || current.physical_size != viewport.physical_size
+        }) {
+            camera.viewport = Some(viewport);
+        }
+    }
+}
+
+// 領域のエントリを表示する（表示中のものは呼び出し側で消す）
+//
+// エントリと、実際に何か表示したか（空のテキストなどは表示しない）を返す
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_entry(
+    cmds: &mut Commands,
+    region: &Region,
+    index: usize,
+    fonts: &Fonts,
+    config: &Config,
+    paths: &DataPaths,
+    asset_server: &AssetServer,
+    bingo: &BingoState,
+    variables: &Variables,
+) -> Option<(TextSource, bool)> {
+    let text_source = region.queue.texts.get(index)?;
+    let style = EntryStyle::from_source(text_source, fonts, config, region, paths, asset_server);
+    let context = TemplateContext {
+        entry: region.queue.entry_info(index),
+        bingo,
+        variables,
+    };
+    let shown = if text_source.duration == 0.0 {
+        text_spawner::spawn_static_text(cmds, &text_source.content, &style, region, config, &context);
+        true
+    } else {
+        text_spawner::spawn_text(cmds, &text_source.content, &text_source.duration, &style, region, config, &context)
+    };
+    Some((text_source.clone(), shown))
+}
+
+impl Region {
+    // autoplay の一フレーム分（spawn は番号のエントリを表示し、表示できたら true を返す）
+    //
+    // 表示中のものを消す場合は true を返す
+    fn autoplay_step(&mut self, showing: bool, elapsed: f64, mut spawn: impl FnMut(&Region, usize) -> bool) -> bool {
+        if showing {
+            // 自分では終わらないエントリ（静止・ループ）は hold 秒で次に進む
+            if self.hold_until.is_some_and(|until| elapsed >= until) {
+                self.hold_until = None;
+                return true;
+            }
+            return false;
+        }
+        self.hold_until = None;
+        if self.retry_at.is_some_and(|at| elapsed < at) {
+            return false;
+        }
+        self.retry_at = None;
+        // 表示できないエントリは飛ばす（一周しても表示できなければ hold 秒後にもう一度試す）
+        for _ in 0..self.queue.texts.len() {
+            if self.queue.current_index >= self.queue.texts.len() {
+                self.queue.current_index = 0;
+            }
+            let index = self.queue.current_index;
+            self.queue.current_index += 1;
+            if spawn(self, index) {
+                self.scrolling.is_active = true;
+                let duration = self.queue.texts[index].duration;
+                self.hold_until = text_spawner::never_ends(duration).then_some(elapsed + self.hold as f64);
+                return false;
+            }
+        }
+        self.retry_at = Some(elapsed + self.hold as f64);
+        false
+    }
+}
+
+// autoplay の領域は、表示が終わったら次のエントリを表示する（最後まで行ったら先頭に戻る）
+#[allow(clippy::too_many_arguments)]
+pub fn autoplay_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut regions: ResMut<Regions>,
+    fonts: Res<Fonts>,
+    config: Res<Config>,
+    paths: Res<DataPaths>,
+    asset_server: Res<AssetServer>,
+    bingo: Res<BingoState>,
+    variables: Res<Variables>,
+    text_query: ShowingQuery,
+) {
+    let elapsed = time.elapsed_secs_f64();
+    for region in &mut regions.regions {
+        if !region.autoplay || region.queue.texts.is_empty() {
+            continue;
+        }
+        let showing = text_query.iter().any(|(_, _, id)| id.copied().unwrap_or_default() == region.id);
+        let dismiss = region.autoplay_step(showing, elapsed, |region, index| {
+            spawn_entry(&mut cmds, region, index, &fonts, &config, &paths, &asset_server, &bingo, &variables)
+                .is_some_and(|(_, shown)| shown)
+        });
+        if dismiss {
+            transition::dismiss_all(&mut cmds, &text_query, region.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(texts: Vec<TextSource>) -> Region {
+        let queue = TextQueue {
+            texts,
+            current_index: 0,
+            current_preset: "test".to_string(),
+        };
+        let mut region = Region::new(RegionId(0), "main", 0, Rect::new(0.0, 0.0, 1920.0, 1080.0), queue);
+        region.autoplay = true;
+        region
+    }
+
+    #[test]
+    fn static_entry_is_dismissed_after_hold() {
+        let mut region = region(vec![TextSource::new("静止", 0.0), TextSource::new("流れる", 10.0)]);
+        region.hold = 3.0;
+        let mut spawned = Vec::new();
+
+        assert!(!region.autoplay_step(false, 1.0, |_, index| {
+            spawned.push(index);
+            true
+        }));
+        assert_eq!(spawned, [0]);
+        // 表示中は hold 秒たつまで消さない
+        assert!(!region.autoplay_step(true, 3.9, |_, _| unreachable!()));
+        assert!(region.autoplay_step(true, 4.0, |_, _| unreachable!()));
+
+        // 消えたら次のエントリを表示する（流れるエントリは自分で終わるので消さない）
+        assert!(!region.autoplay_step(false, 4.1, |_, index| {
+            spawned.push(index);
+            true
+        }));
+        assert_eq!(spawned, [0, 1]);
+        assert!(!region.autoplay_step(true, 100.0, |_, _| unreachable!()));
+    }
+
+    #[test]
+    fn entries_that_show_nothing_are_skipped() {
+        let mut region = region(vec![
+            TextSource::new("{{empty}}", 10.0),
+            TextSource::new("表示する", 10.0),
+            TextSource::new("{{empty}}", 10.0),
+        ]);
+        let mut tried = Vec::new();
+        region.autoplay_step(false, 0.0, |_, index| {
+            tried.push(index);
+            index == 1
+        });
+        // 表示できないエントリは同じフレームで飛ばす
+        assert_eq!(tried, [0, 1]);
+        assert_eq!(region.queue.current_index, 2);
+    }
+
+    #[test]
+    fn waits_for_hold_when_no_entry_shows_anything() {
+        let mut region = region(vec![TextSource::new("{{a}}", 10.0), TextSource::new("{{b}}", 10.0)]);
+        region.hold = 2.0;
+        let mut tried = 0;
+        region.autoplay_step(false, 0.0, |_, _| {
+            tried += 1;
+            false
+        });
+        assert_eq!(tried, 2);
+
+        // 毎フレーム作り直さず、hold 秒たってからもう一度試す
+        region.autoplay_step(false, 1.0, |_, _| unreachable!());
+        region.autoplay_step(false, 2.0, |_, _| {
+            tried += 1;
+            false
+        });
+        assert_eq!(tried, 4);
+    }
+}
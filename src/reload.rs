@@ -0,0 +1,183 @@
+use bevy::{
+    time::{Timer, TimerMode},
+    prelude::*,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cli::Cli;
+use crate::diagnostics::{Diagnostics, Report};
+use crate::loader::{self, Config, DataPaths, PresetManager};
+use crate::server::{PresetsChangedResponse, ResponseBroadcaster, WsResponse};
+use crate::regions::{RegionCamera, Regions};
+use crate::{Fonts, TextQueue};
+
+// ファイルの更新時刻の一覧（パス → 最終更新時刻）
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+#[derive(Resource)]
+pub struct ReloadWatcher {
+    pub timer: Timer,
+    config_snapshot: Snapshot,
+    presets_snapshot: Snapshot,
+}
+
+impl ReloadWatcher {
+    pub fn new(interval_secs: f32, paths: &DataPaths) -> Self {
+        Self {
+            timer: Timer::from_seconds(interval_secs, TimerMode::Repeating),
+            config_snapshot: snapshot_file(&paths.config),
+            presets_snapshot: snapshot_dir(&paths.presets),
+        }
+    }
+}
+
+fn snapshot_file(path: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    if let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified()) {
+        snapshot.insert(path.to_path_buf(), modified);
+    }
+    snapshot
+}
+
+fn snapshot_dir(dir: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+    snapshot
+}
+
+// 一定間隔で更新時刻を比較し、変更があれば再読み込みする
+#[allow(clippy::too_many_arguments)]
+pub fn reload_system(
+    time: Res<Time>,
+    mut watcher: ResMut<ReloadWatcher>,
+    cli: Res<Cli>,
+    mut paths: ResMut<DataPaths>,
+    mut config: ResMut<Config>,
+    mut fonts: ResMut<Fonts>,
+    asset_server: Res<AssetServer>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut preset_manager: ResMut<PresetManager>,
+    mut regions: ResMut<Regions>,
+    mut camera_query: Query<(&mut Transform, &RegionCamera)>,
+    broadcaster: Option<Res<ResponseBroadcaster>>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let config_snapshot = snapshot_file(&paths.config);
+    if config_snapshot != watcher.config_snapshot {
+        watcher.config_snapshot = config_snapshot;
+        let mut report = Report::default();
+        let result = loader::load_config(&paths.config, &mut report).map(|mut new_config| {
+            cli.apply(&mut new_config);
+            new_config
+        });
+        if let Some(new_config) = &result {
+            preset_manager.check_config(new_config, &paths.config, &mut report);
+        }
+        replace_report(&mut diagnostics, |d| &mut d.config, report);
+        match result {
+            Some(new_config) => {
+                paths.apply_config(&new_config);
+                preset_manager.apply_config(&new_config);
+                // normalize_width などの読み込み設定を反映するためプリセットも読み直す
+                watcher.presets_snapshot.clear();
+                *fonts = Fonts::load(&new_config, &paths, &asset_server);
+                // camera_offset はメインのウィンドウのみ（[[windows]] の変更は再起動が必要）
+                regions.displays[0].camera_offset = new_config.camera_offset;
+                for (mut transform, camera) in &mut camera_query {
+                    if regions.get(camera.0).window == 0 {
+                        transform.translation.x = new_config.camera_offset;
+                    }
+                }
+                *config = new_config;
+                println!("Reloaded config");
+            }
+            None => {
+                // 読み込みに失敗した場合は現在の設定を維持
+                println!("Err: Can't Reload Config File");
+            }
+        }
+    }
+
+    let presets_snapshot = snapshot_dir(&paths.presets);
+    if presets_snapshot != watcher.presets_snapshot {
+        watcher.presets_snapshot = presets_snapshot;
+        let mut report = Report::default();
+        let result = loader::load_all_presets(&paths.presets, &config, &mut report);
+        if let Err(e) = &result {
+            report.error(&paths.presets, None, format!("can't load presets: {}", e));
+        }
+        replace_report(&mut diagnostics, |d| &mut d.presets, report);
+        match result {
+            Ok(presets) => {
+                *preset_manager = PresetManager::new(presets, &config);
+                for region in &mut regions.regions {
+                    sync_text_queue(&mut region.queue, &preset_manager, &config);
+                }
+
+                let preset_names = preset_manager.names();
+                println!("Reloaded presets: {:?}", preset_names);
+
+                if let Some(broadcaster) = broadcaster {
+                    let response = WsResponse::PresetsChanged(PresetsChangedResponse {
+                        event: "presets_changed".to_string(),
+                        presets: preset_names,
+                    });
+                    let _ = broadcaster.sender.send(response);
+                }
+            }
+            Err(e) => {
+                // 読み込みに失敗した場合は現在のプリセットを維持
+                println!("Err: Can't Reload Presets: {}", e);
+            }
+        }
+    }
+}
+
+// 診断結果を差し替え、新しい問題があればバナーを再表示する
+fn replace_report(
+    diagnostics: &mut Diagnostics,
+    target: impl Fn(&mut Diagnostics) -> &mut Report,
+    report: Report,
+) {
+    for diagnostic in &report.items {
+        eprintln!("{}", diagnostic);
+    }
+    if report.has_errors() {
+        diagnostics.dismissed = false;
+    }
+    *target(diagnostics) = report;
+}
+
+// 再読み込み後も同じプリセット・同じ位置を指すように TextQueue を更新
+fn sync_text_queue(text_queue: &mut TextQueue, preset_manager: &PresetManager, config: &Config) {
+    if let Some(preset) = preset_manager.get(&text_queue.current_preset) {
+        text_queue.texts = preset.texts.clone();
+        if text_queue.current_index >= text_queue.texts.len() {
+            text_queue.current_index = 0;
+        }
+        return;
+    }
+
+    // 現在のプリセットが削除された場合はデフォルトのプリセットの先頭に切り替え
+    if let Some(preset) = preset_manager.default_preset(config) {
+        println!(
+            "Preset '{}' was removed, switched to preset: {}",
+            text_queue.current_preset, preset.name
+        );
+        text_queue.texts = preset.texts.clone();
+        text_queue.current_preset = preset.name.clone();
+        text_queue.current_index = 0;
+    }
+}
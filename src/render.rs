@@ -0,0 +1,135 @@
+use bevy::{asset::LoadState, prelude::*, time::TimeUpdateStrategy};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::bingo::BingoState;
+use crate::capture::{Capture, CapturedFrame};
+use crate::fit::Fitting;
+use crate::fonts::FontFallbackPending;
+use crate::images::EntryImage;
+use crate::loader::{Config, DataPaths};
+use crate::regions::{self, RegionId, Regions};
+use crate::snapshot;
+use crate::template::Variables;
+use crate::text_spawner::PendingLayout;
+use crate::Fonts;
+
+// render の 1 フレームの時間（実際の時間によらず、この時間ずつ進める）
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// 配置が決まらないまま待つ最大のフレーム数（画像が読み込めない場合など）
+const MAX_WAIT_FRAMES: u32 = 300;
+
+// render サブコマンドの作業（最初の領域にエントリを表示し、at 秒後の画面を書き出す）
+#[derive(Resource)]
+pub struct RenderJob {
+    pub preset: String,
+    pub index: usize,
+    pub at: f32,
+    pub output: PathBuf,
+    state: RenderState,
+}
+
+enum RenderState {
+    Loading,                      // フォントの読み込み待ち
+    Layout { frames: u32 },       // 表示したエントリの大きさ・位置が決まるのを待つ
+    Playing { until: f64 },       // at 秒まで進める
+    Capturing { requested: u64 }, // 時間を止めて読み出しを待つ
+}
+
+impl RenderJob {
+    pub fn new(preset: &str, index: usize, at: f32, output: PathBuf) -> Self {
+        Self {
+            preset: preset.to_string(),
+            index,
+            at,
+            output,
+            state: RenderState::Loading,
+        }
+    }
+}
+
+// フォントの読み込みが終わったか（読み込めなかったフォントも終わったものとする）
+pub fn fonts_loaded(fonts: &Fonts, asset_server: &AssetServer) -> bool {
+    let mut handles = std::iter::once(&fonts.text_font.font).chain(&fonts.fallback).chain(fonts.named.values());
+    !handles.any(|handle| matches!(asset_server.load_state(handle), LoadState::Loading))
+}
+
+// フォントを読み込んだらエントリを表示する
+#[allow(clippy::too_many_arguments)]
+pub fn render_start_system(
+    mut cmds: Commands,
+    mut job: ResMut<RenderJob>,
+    mut regions: ResMut<Regions>,
+    fonts: Res<Fonts>,
+    config: Res<Config>,
+    paths: Res<DataPaths>,
+    asset_server: Res<AssetServer>,
+    bingo: Res<BingoState>,
+    variables: Res<Variables>,
+) {
+    if !matches!(job.state, RenderState::Loading) || !fonts_loaded(&fonts, &asset_server) {
+        return;
+    }
+    let region = regions.get_mut(RegionId(0));
+    regions::spawn_entry(&mut cmds, region, job.index, &fonts, &config, &paths, &asset_server, &bingo, &variables);
+    region.queue.current_index = job.index + 1;
+    region.scrolling.is_active = true;
+    job.state = RenderState::Layout { frames: 0 };
+}
+
+// at 秒まで進めたら時間を止め、読み出した画面を PNG に書き出して終了する
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn render_capture_system(
+    mut job: ResMut<RenderJob>,
+    time: Res<Time>,
+    mut time_update: ResMut<TimeUpdateStrategy>,
+    mut capture: ResMut<Capture>,
+    frame: Res<CapturedFrame>,
+    pending: Query<(), Or<(With<PendingLayout>, With<Fitting>, With<FontFallbackPending>)>>,
+    images: Query<&EntryImage>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    // 状態が変わったフレームのうちに次の状態も確かめる（at = 0 なら大きさが決まったフレームを書き出す）
+    if let RenderState::Layout { frames } = job.state {
+        // at は大きさ・位置が決まってスクロールが始まった時点から数える
+        let laid_out = pending.is_empty() && images.iter().all(|image| image.block.is_some());
+        if !laid_out && frames < MAX_WAIT_FRAMES {
+            job.state = RenderState::Layout { frames: frames + 1 };
+            return;
+        }
+        if !laid_out {
+            println!("Err: Entry layout did not finish (image not found?)");
+        }
+        job.state = RenderState::Playing {
+            until: time.elapsed_secs_f64() + job.at as f64,
+        };
+    }
+    if let RenderState::Playing { until } = job.state {
+        // 浮動小数点の誤差で 1 フレーム余計に進めないよう、半フレーム手前で止める
+        if time.elapsed_secs_f64() + FRAME_TIME.as_secs_f64() / 2.0 < until {
+            return;
+        }
+        *time_update = TimeUpdateStrategy::ManualDuration(Duration::ZERO);
+        job.state = RenderState::Capturing { requested: frame.count };
+    }
+    if let RenderState::Capturing { requested } = job.state {
+        capture.requested = true;
+        if !snapshot::is_fresh(&frame, requested) {
+            return;
+        }
+        let result = snapshot::encode_png(frame.size, &frame.data)
+            .map_err(|e| e.to_string())
+            .and_then(|png| std::fs::write(&job.output, png).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => {
+                println!("Rendered {} #{} at {}s to {:?}", job.preset, job.index, job.at, job.output);
+                exit.write(AppExit::Success);
+            }
+            Err(e) => {
+                println!("Err: Can't write {:?}: {}", job.output, e);
+                exit.write(AppExit::error());
+            }
+        }
+    }
+}
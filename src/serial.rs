@@ -0,0 +1,263 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+
+use crate::loader::SerialFormat;
+
+// 開けなかった・切断された場合に開き直すまでの間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// 通信速度の既定値
+pub const DEFAULT_BAUD: u32 = 115200;
+
+// フレームの先頭（"NB"）
+const MAGIC: [u8; 2] = *b"NB";
+
+// シリアルのフレーム
+//
+// | 位置 | 長さ | 内容 |
+// | 0 | 2 | "NB" |
+// | 2 | 1 | 形式（0 = RGB888、1 = RGB565） |
+// | 3 | 1 | 通し番号（0〜255 を繰り返す） |
+// | 4 | 2 | 幅（リトルエンディアン、以下同じ） |
+// | 6 | 2 | 高さ |
+// | 8 | 4 | 画素データの長さ |
+// | 12 | N | 画素データ（RGB888 は 3 バイト、RGB565 は 2 バイト） |
+// | 12 + N | 2 | 0〜12 + N の CRC-16/CCITT-FALSE |
+pub fn frame(format: SerialFormat, sequence: u8, width: u32, height: u32, channels: &[u8]) -> Vec<u8> {
+    let payload: Vec<u8> = match format {
+        SerialFormat::Rgb888 => channels.to_vec(),
+        SerialFormat::Rgb565 => channels
+            .chunks(3)
+            .flat_map(|c| {
+                let value = ((c[0] as u16 >> 3) << 11) | ((c[1] as u16 >> 2) << 5) | (c[2] as u16 >> 3);
+                value.to_le_bytes()
+            })
+            .collect(),
+    };
+    let mut frame = Vec::with_capacity(14 + payload.len());
+    frame.extend_from_slice(&MAGIC);
+    frame.push(match format {
+        SerialFormat::Rgb888 => 0,
+        SerialFormat::Rgb565 => 1,
+    });
+    frame.push(sequence);
+    frame.extend_from_slice(&(width as u16).to_le_bytes());
+    frame.extend_from_slice(&(height as u16).to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+// CRC-16/CCITT-FALSE（多項式 0x1021、初期値 0xffff）
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// シリアルデバイス（またはファイル・pty）への書き込み
+//
+// 書き込みは別スレッドで行い、書き込みが追いつかない間のフレームは捨てる。
+pub struct SerialOutput {
+    frames: SyncSender<Vec<u8>>,
+    pub sequence: u8,
+}
+
+impl SerialOutput {
+    pub fn open(device: &str, baud: u32) -> Self {
+        let (frames, receiver) = mpsc::sync_channel(1);
+        let device = PathBuf::from(device);
+        std::thread::spawn(move || write_loop(device, baud, receiver));
+        Self { frames, sequence: 0 }
+    }
+
+    pub fn send(&mut self, frame: Vec<u8>) {
+        if self.frames.try_send(frame).is_ok() {
+            self.sequence = self.sequence.wrapping_add(1);
+        }
+    }
+}
+
+// 開けない・書き込めない間は RETRY_INTERVAL ごとに開き直す
+fn write_loop(device: PathBuf, baud: u32, receiver: Receiver<Vec<u8>>) {
+    let mut file = None;
+    let mut reported = false;
+    while let Ok(frame) = receiver.recv() {
+        if file.is_none() {
+            // ケーブルが抜けている間にファイルを作ってしまわないよう create はしない
+            match OpenOptions::new().append(true).open(&device) {
+                Ok(opened) => {
+                    // 挿し直すと端末の設定は元に戻るので、開くたびに設定する
+                    if let Err(e) = configure(&opened, baud) {
+                        println!("Err: Can't configure serial output {:?}: {}", device, e);
+                    }
+                    println!("Serial output connected: {:?}", device);
+                    file = Some(opened);
+                    reported = false;
+                }
+                Err(e) => {
+                    if !reported {
+                        println!("Err: Can't open serial output {:?}: {} (retrying)", device, e);
+                        reported = true;
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                    continue;
+                }
+            }
+        }
+        if let Some(opened) = &mut file
+            && let Err(e) = opened.write_all(&frame).and_then(|()| opened.flush())
+        {
+            println!("Err: Serial output {:?} disconnected: {}", device, e);
+            file = None;
+        }
+    }
+}
+
+// 端末（USB シリアルなど）なら、送ったバイト列をそのまま通すようにして通信速度を設定する
+//
+// 端末の既定の設定では 0x0a が 0x0d 0x0a に変わるなど、フレームが壊れる。
+// ファイル・FIFO はそのまま書き込む。
+#[cfg(unix)]
+fn configure(file: &File, baud: u32) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let fd = file.as_raw_fd();
+    // SAFETY: fd は file が持つ開いている番号で、termios はこの関数の中でだけ使う
+    unsafe {
+        if libc::isatty(fd) == 0 {
+            return Ok(());
+        }
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut termios);
+        if let Some(speed) = speed(baud)
+            && libc::cfsetspeed(&mut termios, speed) < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn configure(_file: &File, _baud: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn speed(baud: u32) -> Option<libc::speed_t> {
+    let speed = match baud {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        #[cfg(target_os = "linux")]
+        460800 => libc::B460800,
+        #[cfg(target_os = "linux")]
+        921600 => libc::B921600,
+        #[cfg(target_os = "linux")]
+        1000000 => libc::B1000000,
+        #[cfg(target_os = "linux")]
+        2000000 => libc::B2000000,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+// 設定できる通信速度か
+#[cfg(unix)]
+pub fn supported_baud(baud: u32) -> bool {
+    speed(baud).is_some()
+}
+
+#[cfg(not(unix))]
+pub fn supported_baud(_baud: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+        assert_eq!(crc16(b""), 0xffff);
+    }
+
+    #[test]
+    fn frame_layout() {
+        let channels: Vec<u8> = (1..=6).collect();
+        let frame = frame(SerialFormat::Rgb888, 7, 2, 1, &channels);
+        assert_eq!(frame.len(), 12 + 6 + 2);
+        assert_eq!(&frame[..2], b"NB");
+        assert_eq!(frame[2], 0);
+        assert_eq!(frame[3], 7);
+        assert_eq!(&frame[4..6], &2u16.to_le_bytes());
+        assert_eq!(&frame[6..8], &1u16.to_le_bytes());
+        assert_eq!(&frame[8..12], &6u32.to_le_bytes());
+        assert_eq!(&frame[12..18], &channels[..]);
+        assert_eq!(&frame[18..], &crc16(&frame[..18]).to_le_bytes());
+    }
+
+    #[test]
+    fn packs_rgb565() {
+        let channels = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let frame = frame(SerialFormat::Rgb565, 0, 4, 1, &channels);
+        assert_eq!(frame[2], 1);
+        assert_eq!(&frame[8..12], &8u32.to_le_bytes());
+        assert_eq!(&frame[12..20], &[0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00, 0xff, 0xff]);
+        assert_eq!(frame.len(), 12 + 8 + 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn configures_terminal_as_raw() {
+        use std::os::fd::{AsRawFd, FromRawFd};
+        // SAFETY: 開いた pty の番号は File が持つ
+        let (master, path) = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(fd >= 0);
+            assert_eq!(libc::grantpt(fd), 0);
+            assert_eq!(libc::unlockpt(fd), 0);
+            let name = std::ffi::CStr::from_ptr(libc::ptsname(fd)).to_string_lossy().into_owned();
+            (File::from_raw_fd(fd), name)
+        };
+        let slave = OpenOptions::new().append(true).open(&path).unwrap();
+        configure(&slave, 921600).unwrap();
+
+        // SAFETY: slave が開いている間だけ使う
+        let termios = unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            assert_eq!(libc::tcgetattr(slave.as_raw_fd(), &mut termios), 0);
+            termios
+        };
+        assert_eq!(termios.c_oflag & libc::OPOST, 0);
+        assert_eq!(termios.c_lflag & (libc::ICANON | libc::ECHO), 0);
+        assert_eq!(unsafe { libc::cfgetospeed(&termios) }, libc::B921600);
+        drop(master);
+
+        // 端末でないファイルはそのまま
+        let file = File::open("/dev/null").unwrap();
+        assert!(configure(&file, DEFAULT_BAUD).is_ok());
+        assert!(supported_baud(115200));
+        assert!(!supported_baud(12345));
+    }
+}
@@ -1,27 +1,38 @@
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade}, 
-    response::IntoResponse, 
+    http::{header, StatusCode},
+    response::{IntoResponse, Response}, 
     routing::get, 
     Extension, 
     Router
 };
 use bevy_tokio_tasks::TokioTasksRuntime;
 use bevy::prelude::*;
-use tokio::sync::{mpsc, broadcast};
+use tokio::sync::{mpsc, broadcast, oneshot};
 use serde::{Deserialize, Serialize};
 use futures_util::{SinkExt, StreamExt};
+use std::collections::BTreeMap;
+
+use crate::regions::{RegionId, Regions};
+use crate::snapshot::{self, SnapshotReply, SnapshotRequests};
+use crate::template::{TemplateContext, Variables};
+
+pub const DEFAULT_LISTEN: &str = "0.0.0.0:3000";
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(tag = "mode")]
 pub enum WsCommand {
     #[serde(rename = "bulletin")]
-    Bulletin { preset: String, index: u32 },
+    Bulletin { preset: String, index: u32, window: Option<String>, region: Option<String> },
     #[serde(rename = "bingo")]
-    Bingo { method: BingoMethod },
+    Bingo { method: BingoMethod, window: Option<String>, region: Option<String> },
     #[serde(rename = "countdown")]
-    Countdown { method: CountdownMethod, seconds: Option<f32>, countdown_mode: Option<String> },
+    Countdown { method: CountdownMethod, seconds: Option<f32>, countdown_mode: Option<String>, window: Option<String>, region: Option<String> },
     #[serde(rename = "list_presets")]
     ListPresets,
+    // テンプレート変数の設定（value が null の場合は削除）
+    #[serde(rename = "set_var")]
+    SetVar { name: String, value: Option<String> },
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -43,10 +54,14 @@ pub enum WsResponse {
     Bingo(BingoResponse),
     Countdown(CountdownResponse),
     PresetList(PresetListResponse),
+    PresetsChanged(PresetsChangedResponse),
+    Variables(VariablesResponse),
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BulletinResponse {
+    pub window: String,
+    pub region: String,
     pub prev_text: String,
     pub now_text: String,
     pub next_text: String,
@@ -66,6 +81,28 @@ pub struct CountdownResponse {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PresetListResponse {
     pub presets: Vec<String>,
+    pub details: Vec<PresetSummary>,
+    pub default_preset: String,
+    pub current_preset: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PresetSummary {
+    pub name: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PresetsChangedResponse {
+    pub event: String,
+    pub presets: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct VariablesResponse {
+    pub variables: BTreeMap<String, String>,
 }
 
 #[derive(Resource)]
@@ -101,33 +138,47 @@ pub fn setup_websocket_server(app: &mut App) {
         sender: response_tx,
     });
     
+    app.init_resource::<SnapshotRequests>();
+    
     app.add_systems(Startup, start_axum_server);
     app.add_systems(Update, handle_websocket_commands);
+    app.add_systems(Update, snapshot::snapshot_system.before(crate::capture::readback_system));
 }
 
 fn start_axum_server(
     runtime: Res<TokioTasksRuntime>,
     command_sender: Res<CommandSender>,
     response_broadcaster: Res<ResponseBroadcaster>,
+    snapshot_requests: Res<SnapshotRequests>,
+    config: Res<crate::loader::Config>,
 ) {
     let command_tx = command_sender.sender.clone();
     let response_tx = response_broadcaster.sender.clone();
+    let snapshot_tx = snapshot_requests.sender.clone();
+    let listen = config.listen.clone().unwrap_or_else(|| DEFAULT_LISTEN.to_string());
     
     runtime.spawn_background_task(move |_ctx| async move {
         let app = Router::new()
             .route("/ws", get(ws_handler))
+            .route("/snapshot.png", get(snapshot_handler))
             .layer(Extension(command_tx))
-            .layer(Extension(response_tx));
+            .layer(Extension(response_tx))
+            .layer(Extension(snapshot_tx));
             
-        let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
-            .await
-            .expect("Failed to bind to address");
+        let listener = match tokio::net::TcpListener::bind(&listen).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Can't listen on {}: {}", listen, e);
+                return;
+            }
+        };
             
-        println!("WebSocket server running on ws://0.0.0.0:3000/ws");
+        println!("WebSocket server running on ws://{}/ws", listen);
+        println!("Snapshot available at http://{}/snapshot.png", listen);
         
-        axum::serve(listener, app)
-            .await
-            .expect("Server failed to start");
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Server stopped: {}", e);
+        }
     });
 }
 
@@ -139,6 +190,21 @@ async fn ws_handler(
     ws.on_upgrade(move |socket| handle_websocket(socket, command_tx, response_tx))
 }
 
+// メインのウィンドウの内容を PNG で返す（--headless か [[outputs]] がある場合のみ）
+async fn snapshot_handler(
+    Extension(snapshot_tx): Extension<mpsc::Sender<SnapshotReply>>,
+) -> Response {
+    let (reply, receiver) = oneshot::channel();
+    if snapshot_tx.send(reply).await.is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    match receiver.await {
+        Ok(Ok(png)) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        Ok(Err(e)) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
 async fn handle_websocket(
     socket: WebSocket,
     command_tx: mpsc::Sender<WsCommand>,
@@ -200,70 +266,72 @@ async fn handle_websocket(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_websocket_commands(
     mut commands: Commands,
     mut ws_channel: ResMut<WebSocketChannel>,
-    mut text_queue: ResMut<crate::TextQueue>,
+    mut regions: ResMut<Regions>,
     preset_manager: Res<crate::loader::PresetManager>,
     mut bingo_state: ResMut<crate::bingo::BingoState>,
+    mut variables: ResMut<Variables>,
     mut countdown_timer: ResMut<crate::countdown::CountdownTimer>,
-    mut scrolling_state: ResMut<crate::ScrollingState>,
-    mut scrolling_speed: ResMut<crate::ScrollingSpeed>,
     config: Res<crate::loader::Config>,
     fonts: Res<crate::Fonts>,
-    text_query: Query<Entity, With<crate::Showing>>,
+    asset_server: Res<AssetServer>,
+    paths: Res<crate::loader::DataPaths>,
+    text_query: crate::transition::ShowingQuery,
 ) {
     while let Ok(command) = ws_channel.command_receiver.try_recv() {
         match command {
-            WsCommand::Bulletin { preset, index } => {
+            WsCommand::Bulletin { preset, index, window, region } => {
+                let Some(region_id) = find_region(&regions, window.as_deref(), region.as_deref()) else {
+                    continue;
+                };
+                let window = regions.display(region_id).name.clone();
+                let region = regions.get_mut(region_id);
                 // プリセットが指定されていて、現在のプリセットと異なる場合は切り替え
-                if text_queue.current_preset != preset {
-                    if let Some(new_texts) = preset_manager.presets.get(&preset) {
-                        text_queue.texts = new_texts.clone();
-                        text_queue.current_preset = preset.clone();
-                        text_queue.current_index = 0;
-                        println!("Switched to preset: {}", preset);
+                if region.queue.current_preset != preset {
+                    if let Some(new_preset) = preset_manager.get(&preset) {
+                        region.queue.texts = new_preset.texts.clone();
+                        region.queue.current_preset = preset.clone();
+                        region.queue.current_index = 0;
+                        println!("Switched to preset: {} ({})", preset, region.name);
                     } else {
-                        println!("Preset '{}' not found, using current preset '{}'", preset, text_queue.current_preset);
+                        println!("Preset '{}' not found, using current preset '{}'", preset, region.queue.current_preset);
                     }
                 }
                 
                 // 現在のテキストを削除
-                for entity in text_query.iter() {
-                    commands.entity(entity).despawn();
-                }
+                crate::transition::dismiss_all(&mut commands, &text_query, region.id);
                 
                 // 新しいテキストをスポーン
-                if let Some(text_source) = text_queue.texts.get(index as usize) {
-                    let text_content = text_source.content.clone();
-                    let text_duration = text_source.duration;
-
-		    if text_duration == 0.0 {
-			crate::text_spawner::spawn_static_text(&mut commands, &text_content, fonts.text_font.clone());
-		    } else {
-                    crate::text_spawner::spawn_text(
-                        &mut commands,
-                        &text_content,
-                        &text_duration,
-                        fonts.text_font.clone(),
-                        &config,
-                        &mut *scrolling_speed,
-                    );
-		    }
-                    
-                    text_queue.current_index = index as usize;
-                    scrolling_state.is_active = true;
+                let spawned = crate::regions::spawn_entry(
+                    &mut commands,
+                    region,
+                    index as usize,
+                    &fonts,
+                    &config,
+                    &paths,
+                    &asset_server,
+                    &bingo_state,
+                    &variables,
+                );
+                if let Some((text_source, _)) = spawned {
+                    region.queue.current_index = index as usize;
+                    region.scrolling.is_active = true;
                     
                     // レスポンスを送信
-                    let prev_text = text_queue.texts.get(index.saturating_sub(1) as usize)
+                    let prev_text = region.queue.texts.get(index.saturating_sub(1) as usize)
                         .map(|t| t.content.clone())
                         .unwrap_or_default();
-                    let now_text = text_content;
-                    let next_text = text_queue.texts.get((index + 1) as usize)
+                    let now_text = text_source.content;
+                    let next_text = region.queue.texts.get((index + 1) as usize)
                         .map(|t| t.content.clone())
                         .unwrap_or_default();
                     
                     let response = WsResponse::Bulletin(BulletinResponse {
+                        window,
+                        region: region.name.clone(),
                         prev_text,
                         now_text,
                         next_text,
@@ -271,22 +339,32 @@ fn handle_websocket_commands(
                     
                     let _ = ws_channel.response_sender.send(response);
                 } else {
-                    println!("Text index {} not found in preset '{}'", index, text_queue.current_preset);
+                    println!("Text index {} not found in preset '{}'", index, region.queue.current_preset);
                 }
             }
-            WsCommand::Bingo { method } => {
+            WsCommand::Bingo { method, window, region } => {
+                let Some(region_id) = find_region(&regions, window.as_deref(), region.as_deref()) else {
+                    continue;
+                };
+                let region = regions.get(region_id);
                 match method {
                     BingoMethod::Next => {
                         // 現在のテキストを削除
-                        for entity in text_query.iter() {
-                            commands.entity(entity).despawn();
-                        }
+                        crate::transition::dismiss_all(&mut commands, &text_query, region.id);
                         
                         if let Some(number) = bingo_state.next() {
+                            let context = TemplateContext {
+                                entry: region.queue.entry_info(region.queue.current_index),
+                                bingo: &bingo_state,
+                                variables: &variables,
+                            };
                             crate::text_spawner::spawn_static_text(
                                 &mut commands,
                                 &number.to_string(),
-                                fonts.text_font.clone(),
+                                &crate::text_spawner::EntryStyle::bingo(&fonts, &config, region),
+                                region,
+                                &config,
+                                &context,
                             );
                             
                             let response = WsResponse::Bingo(BingoResponse {
@@ -299,13 +377,14 @@ fn handle_websocket_commands(
                     }
                 }
             }
-            WsCommand::Countdown { method, seconds, countdown_mode } => {
+            WsCommand::Countdown { method, seconds, countdown_mode, window, region } => {
+                let Some(region_id) = find_region(&regions, window.as_deref(), region.as_deref()) else {
+                    continue;
+                };
                 match method {
                     CountdownMethod::Start => {
                         // 他のテキストを削除
-                        for entity in text_query.iter() {
-                            commands.entity(entity).despawn();
-                        }
+                        crate::transition::dismiss_all(&mut commands, &text_query, region_id);
                         
                         // カウントダウンモードを設定（デフォルト: Decelerated）
                         let mode_value = match countdown_mode.as_deref() {
@@ -318,6 +397,7 @@ fn handle_websocket_commands(
                         let duration = seconds.unwrap_or(15.0); // デフォルト15秒
                         
                         countdown_timer.mode = mode_value;
+                        countdown_timer.region = region_id;
                         countdown_timer.initial_seconds = duration;
                         countdown_timer.timer = bevy::time::Timer::from_seconds(duration, bevy::time::TimerMode::Once);
                         countdown_timer.start();
@@ -336,13 +416,49 @@ fn handle_websocket_commands(
                 }
             }
             WsCommand::ListPresets => {
-                let preset_names: Vec<String> = preset_manager.presets.keys().cloned().collect();
+                let details = preset_manager.presets.iter()
+                    .map(|preset| PresetSummary {
+                        name: preset.name.clone(),
+                        display_name: preset.display_name.clone(),
+                        description: preset.description.clone(),
+                        count: preset.texts.len(),
+                    })
+                    .collect();
                 let response = WsResponse::PresetList(PresetListResponse {
-                    presets: preset_names,
+                    presets: preset_manager.names(),
+                    details,
+                    default_preset: preset_manager.default_preset(&config)
+                        .map(|preset| preset.name.clone())
+                        .unwrap_or_default(),
+                    current_preset: regions.get(RegionId(0)).queue.current_preset.clone(),
+                });
+                let _ = ws_channel.response_sender.send(response);
+            }
+            WsCommand::SetVar { name, value } => {
+                match value {
+                    Some(value) => {
+                        println!("Set variable {} = {}", name, value);
+                        variables.values.insert(name, value);
+                    }
+                    None => {
+                        println!("Removed variable {}", name);
+                        variables.values.remove(&name);
+                    }
+                }
+                let response = WsResponse::Variables(VariablesResponse {
+                    variables: variables.values.clone(),
                 });
                 let _ = ws_channel.response_sender.send(response);
             }
         }
     }
 }
-    
+
+// コマンドの window・region（省略時はメインのウィンドウ、ウィンドウの最初の領域）
+fn find_region(regions: &Regions, window: Option<&str>, name: Option<&str>) -> Option<RegionId> {
+    let region = regions.find(window, name);
+    if region.is_none() {
+        println!("Region not found (window: {:?}, region: {:?})", window, name);
+    }
+    region
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::capture::{self, Capture, CapturedFrame};
+
+// /snapshot.png の返信先（PNG のバイト列、または撮れない理由）
+pub type SnapshotReply = oneshot::Sender<Result<Vec<u8>, String>>;
+
+// HTTP サーバーから届いた /snapshot.png の要求
+#[derive(Resource)]
+pub struct SnapshotRequests {
+    pub sender: mpsc::Sender<SnapshotReply>,
+    receiver: mpsc::Receiver<SnapshotReply>,
+    pending: Vec<(u64, SnapshotReply)>, // 要求を受けたときの読み出し回数
+}
+
+impl Default for SnapshotRequests {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel(16);
+        Self {
+            sender,
+            receiver,
+            pending: Vec::new(),
+        }
+    }
+}
+
+// 読み出した画像（RGBA）を PNG にする
+pub fn encode_png(size: UVec2, rgba: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, size.x, size.y);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&capture::rgb(rgba))?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+// 要求の後に描画したフレームが読み出されたか（読み出しは数フレーム遅れて届くので、2 回目を使う）
+pub fn is_fresh(frame: &CapturedFrame, requested_at: u64) -> bool {
+    frame.count >= requested_at + 2
+}
+
+// /snapshot.png の要求に、メインのウィンドウの内容を PNG にして返す
+pub fn snapshot_system(
+    mut requests: ResMut<SnapshotRequests>,
+    capture: Option<ResMut<Capture>>,
+    frame: Res<CapturedFrame>,
+) {
+    while let Ok(reply) = requests.receiver.try_recv() {
+        if capture.is_none() {
+            let _ = reply.send(Err("Snapshots need --headless or [[outputs]]".to_string()));
+            continue;
+        }
+        requests.pending.push((frame.count, reply));
+    }
+    let Some(mut capture) = capture else {
+        return;
+    };
+    if requests.pending.is_empty() {
+        return;
+    }
+    capture.requested = true;
+    let (ready, waiting): (Vec<_>, Vec<_>) =
+        std::mem::take(&mut requests.pending).into_iter().partition(|(count, _)| is_fresh(&frame, *count));
+    requests.pending = waiting;
+    if ready.is_empty() {
+        return;
+    }
+    let png = encode_png(frame.size, &frame.data).map_err(|e| e.to_string());
+    for (_, reply) in ready {
+        let _ = reply.send(png.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trip() {
+        let size = UVec2::new(3, 2);
+        let rgba: Vec<u8> = (0..size.x * size.y).flat_map(|i| [i as u8 * 40, 255 - i as u8, 7, 255]).collect();
+        let png = encode_png(size, &rgba).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(png));
+        let mut reader = decoder.read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(info.bit_depth, png::BitDepth::Eight);
+        assert_eq!(&buffer[..info.buffer_size()], &capture::rgb(&rgba)[..]);
+    }
+
+    #[test]
+    fn waits_for_second_readback() {
+        let frame = |count| CapturedFrame {
+            count,
+            ..default()
+        };
+        assert!(!is_fresh(&frame(5), 4));
+        assert!(is_fresh(&frame(6), 4));
+    }
+}
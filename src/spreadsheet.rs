@@ -0,0 +1,210 @@
+use calamine::{Data, Reader};
+use csv::StringRecord;
+use std::error::Error;
+use std::path::Path;
+
+use crate::diagnostics::Report;
+use crate::loader::TextSource;
+
+// シート名と、そのシートの項目
+pub type Sheet = (String, Vec<TextSource>);
+
+// Excel (xlsx/xlsm/xls) / LibreOffice (ods) のブックを読み込む
+//
+// シートごとに (シート名, 項目) を返す。各シートの最初の空でない行がヘッダー行で、
+// 列名は CSV と同じ（content, duration, color, ...）。空のシートは読み飛ばす。
+pub fn load(path: &Path, report: &mut Report) -> Result<Vec<Sheet>, Box<dyn Error>> {
+    println!("Reading {:?} as spreadsheet", path);
+    let mut workbook = calamine::open_workbook_auto(path)?;
+
+    let mut sheets = Vec::new();
+    for sheet_name in workbook.sheet_names() {
+        let range = match workbook.worksheet_range(&sheet_name) {
+            Ok(range) => range,
+            Err(e) => {
+                report.error(path, None, format!("sheet `{}`: {}", sheet_name, e));
+                continue;
+            }
+        };
+        // セルの行番号（1始まり）はシート上の位置に合わせる
+        let first_row = range.start().map_or(0, |(row, _)| row as usize) + 1;
+        let mut rows = range
+            .rows()
+            .enumerate()
+            .map(|(index, row)| (first_row + index, row))
+            .filter(|(_, row)| row.iter().any(|cell| *cell != Data::Empty));
+
+        let Some((header_line, header)) = rows.next() else {
+            continue;
+        };
+        let headers: StringRecord = header.iter().map(|cell| cell_to_string(cell).trim().to_string()).collect();
+        let records = rows.map(|(line, row)| {
+            let record = row
+                .iter()
+                .map(|cell| match cell {
+                    Data::Error(e) => Err(format!("cell error: {}", e)),
+                    cell => Ok(cell_to_string(cell)),
+                })
+                .collect::<Result<StringRecord, String>>();
+            (Some(line), record)
+        });
+
+        let mut sheet_report = Report::default();
+        let texts = crate::loader::load_records(path, &headers, Some(header_line), records, &mut sheet_report);
+        for mut diagnostic in sheet_report.items {
+            diagnostic.message = format!("sheet `{}`: {}", sheet_name, diagnostic.message);
+            report.items.push(diagnostic);
+        }
+        sheets.push((sheet_name, texts));
+    }
+    Ok(sheets)
+}
+
+// 数値は 10.0 → "10" のように表示形式で文字列にする
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(s) => s.clone(),
+        cell => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::loader::{load_all_presets, Config};
+    use rust_xlsxwriter::Workbook;
+    use std::path::PathBuf;
+
+    // テストごとに空の一時ディレクトリを作る
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ebb-spreadsheet-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 各シートを (シート名, 行) で書き出す（空文字列のセルは書かない）
+    fn write_book(path: &Path, sheets: &[(&str, &[&[&str]])]) {
+        let mut workbook = Workbook::new();
+        for (name, rows) in sheets {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(*name).unwrap();
+            for (row, cells) in rows.iter().enumerate() {
+                for (col, cell) in cells.iter().enumerate() {
+                    if cell.is_empty() {
+                        continue;
+                    }
+                    match cell.parse::<f64>() {
+                        Ok(number) => worksheet.write_number(row as u32, col as u16, number).unwrap(),
+                        Err(_) => worksheet.write_string(row as u32, col as u16, *cell).unwrap(),
+                    };
+                }
+            }
+        }
+        workbook.save(path).unwrap();
+    }
+
+    #[test]
+    fn finds_header_row_and_reads_optional_columns() {
+        let dir = temp_dir("header");
+        let path = dir.join("book.xlsx");
+        // 上の空行は読み飛ばし、最初の空でない行をヘッダーにする
+        write_book(
+            &path,
+            &[(
+                "Sheet1",
+                &[
+                    &[],
+                    &[],
+                    &["content", "duration", "color"],
+                    &["こんにちは", "10", "red"],
+                    &["色なし", "0", ""],
+                ],
+            )],
+        );
+
+        let mut report = Report::default();
+        let sheets = load(&path, &mut report).unwrap();
+        assert!(report.items.is_empty(), "{:?}", report.items);
+        assert_eq!(sheets.len(), 1);
+        let (name, texts) = &sheets[0];
+        assert_eq!(name, "Sheet1");
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[0].content, "こんにちは");
+        assert_eq!(texts[0].duration, 10.0);
+        assert_eq!(texts[0].color.as_deref(), Some("red"));
+        assert_eq!(texts[1].content, "色なし");
+        assert_eq!(texts[1].duration, 0.0);
+        assert_eq!(texts[1].color, None);
+        assert_eq!(texts[1].font, None);
+    }
+
+    #[test]
+    fn reports_bad_and_empty_cells_with_sheet_row() {
+        let dir = temp_dir("cells");
+        let path = dir.join("book.xlsx");
+        write_book(
+            &path,
+            &[(
+                "朝",
+                &[
+                    &["content", "duration"],
+                    &["", "10"],
+                    &["正常", "10"],
+                    &["秒数がおかしい", "abc"],
+                ],
+            )],
+        );
+
+        let mut report = Report::default();
+        let sheets = load(&path, &mut report).unwrap();
+        assert_eq!(sheets[0].1.len(), 1);
+        assert_eq!(sheets[0].1[0].content, "正常");
+
+        let errors: Vec<_> = report.items.iter().filter(|item| item.severity == Severity::Error).collect();
+        assert_eq!(errors.len(), 2, "{:?}", report.items);
+        assert_eq!(errors[0].line, Some(2));
+        assert_eq!(errors[0].message, "sheet `朝`: empty content");
+        assert_eq!(errors[1].line, Some(4));
+        assert!(errors[1].message.starts_with("sheet `朝`: column `duration`"), "{}", errors[1].message);
+    }
+
+    #[test]
+    fn missing_column_is_reported() {
+        let dir = temp_dir("missing");
+        let path = dir.join("book.xlsx");
+        write_book(&path, &[("Sheet1", &[&["content"], &["こんにちは"]])]);
+
+        let mut report = Report::default();
+        let sheets = load(&path, &mut report).unwrap();
+        assert!(sheets[0].1.is_empty());
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].line, Some(1));
+        assert_eq!(report.items[0].message, "sheet `Sheet1`: missing column `duration`");
+    }
+
+    #[test]
+    fn each_sheet_becomes_a_preset_and_lock_files_are_skipped() {
+        let dir = temp_dir("presets");
+        write_book(
+            &dir.join("book.xlsx"),
+            &[
+                ("朝", &[&["content", "duration"], &["おはよう", "10"]]),
+                ("空", &[]),
+                ("夜", &[&["content", "duration"], &["こんばんは", "10"]]),
+            ],
+        );
+        // Excel が開いている間のロックファイルはブックとして読めない
+        std::fs::write(dir.join("~$book.xlsx"), b"lock").unwrap();
+
+        let mut report = Report::default();
+        let presets = load_all_presets(&dir, &Config::fallback(), &mut report).unwrap();
+        assert!(report.items.is_empty(), "{:?}", report.items);
+        let names: Vec<_> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names, ["book/朝", "book/夜"]);
+        assert_eq!(presets[0].meta.display_name.as_deref(), Some("朝"));
+        assert_eq!(presets[1].texts[0].content, "こんばんは");
+    }
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::bingo::BingoState;
+
+// WebSocket の set_var で設定する変数（{名前} で参照）
+#[derive(Resource, Default)]
+pub struct Variables {
+    pub values: BTreeMap<String, String>,
+}
+
+// 表示中のエントリの情報（{preset}, {index}, {total}）
+#[derive(Clone, Default)]
+pub struct EntryInfo {
+    pub preset: String,
+    pub index: usize, // 0始まり（表示は1始まり）
+    pub total: usize,
+}
+
+// テンプレートの展開に使う値
+pub struct TemplateContext<'a> {
+    pub entry: EntryInfo,
+    pub bingo: &'a BingoState,
+    pub variables: &'a Variables,
+}
+
+// 静止テキストのプレースホルダーを毎秒展開し直すためのコンポーネント
+#[derive(Component)]
+pub struct TextTemplate {
+    pub template: String,
+    pub entry: EntryInfo,
+    pub rendered: String, // 最後に展開した結果
+}
+
+pub fn has_placeholders(template: &str) -> bool {
+    template.contains('{')
+}
+
+// {time} {date} {preset} {index} {total} {bingo.last} {bingo.count} と変数を展開する
+//
+// {time:%H:%M:%S} のように chrono の書式も指定できる。
+// {{ と }} はそのまま { と } になり、不明なプレースホルダーは展開しない。
+pub fn expand(template: &str, context: &TemplateContext) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            result.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        match tail.find('}').filter(|_| tail.starts_with('{')) {
+            Some(end) => {
+                let placeholder = &tail[1..end];
+                match resolve(placeholder, context) {
+                    Some(value) => result.push_str(&value),
+                    None => result.push_str(&tail[..=end]),
+                }
+                rest = &tail[end + 1..];
+            }
+            None => {
+                result.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn resolve(placeholder: &str, context: &TemplateContext) -> Option<String> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name.trim(), Some(format)),
+        None => (placeholder.trim(), None),
+    };
+    let value = match name {
+        "time" => format_now(format.unwrap_or("%H:%M"))?,
+        "date" => format_now(format.unwrap_or("%Y-%m-%d"))?,
+        "preset" => context.entry.preset.clone(),
+        "index" => (context.entry.index + 1).to_string(),
+        "total" => context.entry.total.to_string(),
+        "bingo.last" => context.bingo.last().map(|n| n.to_string()).unwrap_or_default(),
+        "bingo.count" => context.bingo.index.to_string(),
+        name => context.variables.values.get(name)?.clone(),
+    };
+    Some(value)
+}
+
+// 書式が不正な場合は None
+fn format_now(format: &str) -> Option<String> {
+    use std::fmt::Write;
+    let mut s = String::new();
+    write!(s, "{}", chrono::Local::now().format(format)).ok()?;
+    Some(s)
+}
+
+// 1秒ごと、または変数が変わったときに静止テキストを展開し直す
+pub fn template_refresh_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    bingo: Res<BingoState>,
+    variables: Res<Variables>,
+    mut query: Query<(Entity, &mut TextTemplate, &crate::text_spawner::StyledText)>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(1.0, TimerMode::Repeating));
+    timer.tick(time.delta());
+    if !timer.just_finished() && !variables.is_changed() && !bingo.is_changed() {
+        return;
+    }
+
+    for (entity, mut template, styled_text) in &mut query {
+        let context = TemplateContext {
+            entry: template.entry.clone(),
+            bingo: &bingo,
+            variables: &variables,
+        };
+        let expanded = expand(&template.template, &context);
+        if template.rendered != expanded {
+            let style = styled_text.style.clone();
+            crate::text_spawner::insert_spans(&mut cmds.entity(entity), &style.spans(&expanded), &style);
+            template.rendered = expanded;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_with(template: &str, variables: &[(&str, &str)]) -> String {
+        let bingo = BingoState {
+            numbers: vec![7, 42, 13],
+            index: 2,
+        };
+        let variables = Variables {
+            values: variables.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        let context = TemplateContext {
+            entry: EntryInfo {
+                preset: "morning".to_string(),
+                index: 2,
+                total: 5,
+            },
+            bingo: &bingo,
+            variables: &variables,
+        };
+        expand(template, &context)
+    }
+
+    #[test]
+    fn expands_entry_bingo_and_variables() {
+        assert_eq!(expand_with("{preset} {index}/{total}", &[]), "morning 3/5");
+        assert_eq!(expand_with("{bingo.last} ({bingo.count})", &[]), "42 (2)");
+        assert_eq!(expand_with("次は{ room }です", &[("room", "A会議室")]), "次はA会議室です");
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(expand_with("{{preset}}", &[]), "{preset}");
+        assert_eq!(expand_with("a}}b{{c", &[]), "a}b{c");
+        assert_eq!(expand_with("{{{preset}}}", &[]), "{morning}");
+    }
+
+    #[test]
+    fn keeps_unknown_and_unterminated_placeholders() {
+        assert_eq!(expand_with("{unknown} {preset}", &[]), "{unknown} morning");
+        assert_eq!(expand_with("{preset", &[]), "{preset");
+        assert_eq!(expand_with("a } b", &[]), "a } b");
+        assert!(has_placeholders("{x}"));
+        assert!(!has_placeholders("plain"));
+    }
+
+    #[test]
+    fn formats_time() {
+        let time = expand_with("{time:%H:%M:%S}", &[]);
+        assert_eq!(time.len(), 8);
+        assert_eq!(time.matches(':').count(), 2);
+        // 不正な書式は展開しない
+        assert_eq!(expand_with("{date:%Q}", &[]), "{date:%Q}");
+    }
+}
@@ -9,12 +9,123 @@ fn count_ascii(s: &str) -> (usize, usize) {
     })
 }
 
-pub fn calc_text_offset(s: &str, tsize: f32, wwidth: f32) -> f32 {
-    let (ascii, non_ascii) = count_ascii(s);
-    println!("{}", non_ascii * 2 + ascii);
-    return ((non_ascii as f32 * tsize) + (ascii as f32 * tsize * 0.5) + wwidth) / 2.0 + 5.0;
+// 概算の文字列幅（全角は tsize、半角は tsize の半分）
+//
+// レイアウトが終わるまでの仮の位置にだけ使い、スクロールには TextLayoutInfo の実測値を使う
+pub fn estimate_text_width(spans: &[crate::markup::Span], tsize: f32) -> f32 {
+    spans
+        .iter()
+        .map(|span| {
+            let (ascii, non_ascii) = count_ascii(&span.text);
+            let size = tsize * span.scale;
+            (non_ascii as f32 * size) + (ascii as f32 * size * 0.5)
+        })
+        .sum()
 }
 
-pub fn calc_speed(w: f32, d: &f32, wwidth: f32) -> f32 {
-    return (w + wwidth) / d;
+// テキストの中心の開始位置（テキスト全体が画面右端の外にある位置）
+pub fn calc_text_offset(text_width: f32, wwidth: f32) -> f32 {
+    (text_width + wwidth) / 2.0 + 5.0
+}
+
+// 開始位置から、テキスト全体が画面左端を通り過ぎるまでを d 秒で移動する速さ
+pub fn calc_speed(text_width: f32, d: &f32, wwidth: f32) -> f32 {
+    calc_text_offset(text_width, wwidth) * 2.0 / d
+}
+
+// 縦書きで向きが変わる文字（長音記号・括弧）
+fn vertical_form(c: char) -> char {
+    match c {
+        'ー' | '－' | '―' => '｜',
+        '「' => '﹁',
+        '」' => '﹂',
+        '『' => '﹃',
+        '』' => '﹄',
+        '（' => '︵',
+        '）' => '︶',
+        _ => c,
+    }
+}
+
+// 縦書き: 一文字ずつ改行して縦に並べる（元の改行は一文字分の空白にする）
+pub fn to_vertical(spans: &mut [crate::markup::Span]) {
+    for span in spans.iter_mut() {
+        let mut text = String::with_capacity(span.text.len() * 2);
+        for c in span.text.chars() {
+            text.push(if c == '\n' { '\u{3000}' } else { vertical_form(c) });
+            text.push('\n');
+        }
+        span.text = text;
+    }
+    if let Some(last) = spans.last_mut() {
+        last.text.pop();
+    }
+}
+
+// 半角カナ (U+FF61〜U+FF9F) に対応する全角文字
+const HALFWIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+// 全角英数字・記号を半角に、半角カナを全角に揃える
+pub fn normalize_width(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{FF01}'..='\u{FF5E}' => {
+                result.push(char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or(c));
+            }
+            '\u{3000}' => result.push(' '),
+            '\u{FF61}'..='\u{FF9F}' => {
+                let kana = HALFWIDTH_KANA.chars().nth(c as usize - 0xFF61).unwrap_or(c);
+                // 濁点・半濁点は直前の文字と合成する
+                let composed = match chars.peek() {
+                    Some('\u{FF9E}') if kana == 'ウ' => Some('ヴ'),
+                    Some('\u{FF9E}') if matches!(kana, 'カ'..='ト' | 'ハ'..='ホ') && kana != 'ッ' => char::from_u32(kana as u32 + 1),
+                    Some('\u{FF9F}') if matches!(kana, 'ハ'..='ホ') => char::from_u32(kana as u32 + 2),
+                    _ => None,
+                };
+                match composed {
+                    Some(composed) => {
+                        chars.next();
+                        result.push(composed);
+                    }
+                    None => result.push(kana),
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_width_converts_fullwidth_ascii() {
+        assert_eq!(normalize_width("ＡＢＣ１２３！？"), "ABC123!?");
+        assert_eq!(normalize_width("ａ　ｂ"), "a b");
+    }
+
+    #[test]
+    fn normalize_width_converts_halfwidth_kana() {
+        assert_eq!(normalize_width("ｱｲｳｴｵ"), "アイウエオ");
+        assert_eq!(normalize_width("ｯｰ｡｢｣"), "ッー。「」");
+    }
+
+    #[test]
+    fn normalize_width_composes_voiced_marks() {
+        assert_eq!(normalize_width("ｶﾞ"), "ガ");
+        assert_eq!(normalize_width("ﾊﾟﾋﾞ"), "パビ");
+        assert_eq!(normalize_width("ｳﾞ"), "ヴ");
+        // 合成できない文字の後ろでは記号のまま残す
+        assert_eq!(normalize_width("ｱﾞ"), "ア゛");
+        assert_eq!(normalize_width("ｶﾟ"), "カ゜");
+    }
+
+    #[test]
+    fn normalize_width_keeps_other_characters() {
+        assert_eq!(normalize_width("漢字とかな abc"), "漢字とかな abc");
+    }
 }
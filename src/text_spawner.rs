@@ -1,72 +1,475 @@
-use crate::{loader::Config, ScrollingSpeed, Showing, TextScroll, LoopingText};
+use crate::{
+    effects::{self, Effect},
+    fonts::{FontFallbackPending, Fonts},
+    images::{self, EntryImage, ImageSource},
+    fit::{self, Fitting},
+    loader::{AutoFit, Config, DataPaths, ScrollDirection, TextAlign, TextOrientation, TextSource, TransitionKind},
+    markup::{self, Span},
+    template::{self, TemplateContext, TextTemplate},
+    transition,
+    regions::{Region, RegionId, Regions},
+    LoopingText, Showing, TextScroll,
+};
 use bevy::{
     camera::visibility::NoFrustumCulling,
-    color::palettes::tailwind::{SLATE_900, YELLOW_300},
+    color::palettes::{css, tailwind::{SLATE_900, YELLOW_300}},
     prelude::*,
+    sprite::Anchor,
+    text::{TextBounds, TextLayoutInfo},
 };
 
+// エントリごとの見た目
+#[derive(Clone)]
+pub struct EntryStyle {
+    pub text_font: TextFont,
+    pub bold_font: Handle<Font>,
+    pub fallback_fonts: Vec<Handle<Font>>,
+    pub color: Color,
+    pub background: Color,
+    pub align: Option<TextAlign>,
+    pub effects: Vec<Effect>,
+    pub orientation: TextOrientation,
+    pub direction: ScrollDirection,
+    pub fit: AutoFit, // 静止テキストのみ
+    pub wrap: bool,
+    pub fit_scale: f32, // 自動調整後の文字サイズの倍率
+    pub transition: TransitionKind,
+    pub reveal: Option<usize>, // タイプライターで表示済みの文字数（None ならすべて）
+    pub image: Option<ImageSource>,
+}
+
+impl EntryStyle {
+    // 既定の見た目（黄色の文字、濃紺の背景、向きは設定ファイルに従う）
+    pub fn new(fonts: &Fonts, config: &Config, region: &Region) -> Self {
+        let mut text_font = fonts.text_font.clone();
+        // 領域ごとの文字サイズ
+        if let Some(text_size) = region.text_size {
+            text_font.font_size = text_size;
+        }
+        Self {
+            text_font,
+            bold_font: fonts.bold_font.clone(),
+            fallback_fonts: fonts.fallback.clone(),
+            color: Color::Srgba(YELLOW_300),
+            background: Color::Srgba(SLATE_900),
+            align: None,
+            effects: Vec::new(),
+            orientation: config.orientation,
+            direction: config.direction.unwrap_or(ScrollDirection::default_for(config.orientation)),
+            fit: config.auto_fit.mode,
+            wrap: config.auto_fit.wrap,
+            fit_scale: 1.0,
+            transition: config.transition.kind,
+            reveal: None,
+            image: None,
+        }
+    }
+
+    // ビンゴの数字（設定ファイルの bingo_effect を付ける）
+    pub fn bingo(fonts: &Fonts, config: &Config, region: &Region) -> Self {
+        let mut style = Self::new(fonts, config, region);
+        if let Some(effect) = config.bingo_effect.as_deref() {
+            style.effects = effects::parse(effect).unwrap_or_default();
+        }
+        style
+    }
+
+    // プリセットの任意列を反映した見た目
+    pub fn from_source(
+        source: &TextSource,
+        fonts: &Fonts,
+        config: &Config,
+        region: &Region,
+        paths: &DataPaths,
+        asset_server: &AssetServer,
+    ) -> Self {
+        let mut style = Self::new(fonts, config, region);
+        if let Some(color) = source.color.as_deref() {
+            match parse_color(color) {
+                Some(color) => style.color = color,
+                None => println!("Unknown color: {}", color),
+            }
+        }
+        if let Some(background) = source.background.as_deref() {
+            match parse_color(background) {
+                Some(background) => style.background = background,
+                None => println!("Unknown color: {}", background),
+            }
+        }
+        if let Some(font_size) = source.font_size {
+            style.text_font.font_size = font_size;
+        }
+        if let Some(font) = source.font.as_deref() {
+            style.text_font.font = fonts.font(font, asset_server);
+            style.bold_font = style.text_font.font.clone();
+            // エントリのフォントにない文字は通常のフォントで表示する
+            style.fallback_fonts.insert(0, fonts.text_font.font.clone());
+        }
+        style.align = source.align;
+        if let Some(effect) = source.effect.as_deref() {
+            match effects::parse(effect) {
+                Ok(effects) => style.effects = effects,
+                Err(e) => println!("Effect: {}", e),
+            }
+        }
+        if let Some(orientation) = source.orientation {
+            style.orientation = orientation;
+            // 縦書きだけを指定した場合は縦書きの既定の向きにする
+            style.direction = ScrollDirection::default_for(orientation);
+        }
+        if let Some(direction) = source.direction {
+            style.direction = direction;
+        }
+        if let Some(fit) = source.fit {
+            style.fit = fit;
+        }
+        if let Some(transition) = source.transition {
+            style.transition = transition;
+        }
+        if let Some(image) = source.image.as_deref() {
+            let fit = source.image_fit.unwrap_or_default();
+            style.image = Some(ImageSource::load(image, fit, &paths.data_dir, asset_server));
+            // 大きさの自動調整はテキストだけの場合のみ
+            style.fit = AutoFit::Off;
+        }
+        style
+    }
+
+    // 部分ごとのフォントと、字形がない場合に試すフォントの順番
+    pub fn font_chain(&self, span: &Span) -> Vec<Handle<Font>> {
+        let first = if span.bold { &self.bold_font } else { &self.text_font.font };
+        let mut chain = vec![first.clone()];
+        for font in std::iter::once(&self.text_font.font).chain(&self.fallback_fonts) {
+            if !chain.contains(font) {
+                chain.push(font.clone());
+            }
+        }
+        chain
+    }
+
+    // インライン記法を解釈し、縦書きなら縦に並べる
+    pub fn spans(&self, text: &str) -> Vec<Span> {
+        let mut spans = markup::parse(text);
+        if self.orientation == TextOrientation::Vertical {
+            crate::text::to_vertical(&mut spans);
+        }
+        spans
+    }
+
+    fn justify(&self) -> Justify {
+        if self.orientation == TextOrientation::Vertical {
+            return Justify::Center;
+        }
+        match self.align {
+            Some(TextAlign::Left) | None => Justify::Left,
+            Some(TextAlign::Center) => Justify::Center,
+            Some(TextAlign::Right) => Justify::Right,
+        }
+    }
+}
+
+// "#ff0000" / "ff0000" 形式、または CSS の色名（red, white など）
+pub fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if let Ok(color) = Srgba::hex(s) {
+        return Some(Color::Srgba(color));
+    }
+    named_color(s)
+}
+
+// CSS の色名のみ
+pub fn named_color(s: &str) -> Option<Color> {
+    let color = match s.trim().to_ascii_lowercase().as_str() {
+        "black" => css::BLACK,
+        "white" => css::WHITE,
+        "gray" | "grey" => css::GRAY,
+        "red" => css::RED,
+        "orange" => css::ORANGE,
+        "yellow" => css::YELLOW,
+        "lime" => css::LIME,
+        "green" => css::GREEN,
+        "cyan" | "aqua" => css::AQUA,
+        "blue" => css::BLUE,
+        "navy" => css::NAVY,
+        "purple" => css::PURPLE,
+        "magenta" | "fuchsia" => css::FUCHSIA,
+        "pink" => css::PINK,
+        "transparent" => return Some(Color::NONE),
+        _ => return None,
+    };
+    Some(Color::Srgba(color))
+}
+
+// レイアウト（実際の大きさの計測）を待っているスクロールテキスト
+#[derive(Component)]
+pub struct PendingLayout {
+    pub duration: f32,
+}
+
+// duration がこの秒数以上のエントリはループ再生する
+const LOOP_DURATION: f32 = 1000.0;
+
+pub fn is_looping(duration: f32) -> bool {
+    duration >= LOOP_DURATION
+}
+
+// 自分では終わらないエントリ（静止・ループ）
+pub fn never_ends(duration: f32) -> bool {
+    duration == 0.0 || is_looping(duration)
+}
+
 pub fn spawn_text(
     cmds: &mut Commands,
     text: &str,
     duration: &f32,
-    text_font: TextFont,
+    style: &EntryStyle,
+    region: &Region,
     config: &Config,
-    scrolling_speed: &mut ScrollingSpeed,
-) {
-    let text_offset = crate::text::calc_text_offset(text, config.text_size, config.window_width);
-    println!("Offset: {}, Duration: {}", text_offset, duration);
-    
-    // duration が 1000 以上の場合はループ再生
-    if *duration >= 1000.0 {
-        let text_width = text_offset * 2.0; // 概算のテキスト幅
+    context: &TemplateContext,
+) -> bool {
+    // 大きさの自動調整は静止テキストのみ
+    let style = &EntryStyle {
+        fit: AutoFit::Off,
+        ..style.clone()
+    };
+    // プレースホルダーは表示開始時に一度だけ展開する（スクロール中は幅が変わらないように）
+    let spans = style.spans(&template::expand(text, context));
+    // 空のテキスト（未設定の変数だけのものなど）は大きさが決まらず終わらないので表示しない
+    if spans.is_empty() && style.image.is_none() {
+        return false;
+    }
+    // 大きさが分かるまでは画面の外（進行方向の反対側）に置いておく
+    let estimated_width = crate::text::estimate_text_width(&spans, style.text_font.font_size);
+    let direction = style.direction;
+    let parking = -direction.vector() * (direction.along(region.size()) + estimated_width);
+
+    let mut entity = if is_looping(*duration) {
         let loop_speed = 500.0; // 500px/s
-        
+
         cmds.spawn((
-            Text2d::new(text),
-            text_font,
-            TextColor(Color::Srgba(YELLOW_300)),
-            TextBackgroundColor(Color::Srgba(SLATE_900)),
-            Transform::from_translation(Vec3::new(config.window_width / 2.0 + text_width / 2.0 + 50.0, 0.0, 0.0)),
-            TextLayout::default(),
+            Transform::from_translation(parking.extend(0.0)),
+            TextLayout::new_with_justify(style.justify()),
             LoopingText {
-                original_x: text_offset,
-                text_width,
+                start: parking,
+                text_length: estimated_width,
                 loop_speed,
             },
+            direction,
+            region.id,
+            region.id.layer(),
             Showing,
         ))
-        .insert(NoFrustumCulling);
     } else {
         // 通常の1回だけのスクロール
         cmds.spawn((
-            Text2d::new(text),
-            text_font,
-            TextColor(Color::Srgba(YELLOW_300)),
-            TextBackgroundColor(Color::Srgba(SLATE_900)),
-            Transform::from_translation(Vec3::new(text_offset, 0.0, 0.0)),
-            TextLayout::default(),
+            Transform::from_translation(parking.extend(0.0)),
+            TextLayout::new_with_justify(style.justify()),
             TextScroll,
+            direction,
+            region.id,
+            region.id.layer(),
             Showing,
         ))
-        .insert(NoFrustumCulling);
-        scrolling_speed.speed =
-            crate::text::calc_speed(text_offset * 2.0, duration, config.window_width);
+    };
+    insert_spans(&mut entity, &spans, style);
+    entity.insert((NoFrustumCulling, PendingLayout { duration: *duration }));
+    if let Some(image) = &style.image {
+        images::insert_image(&mut entity, image, true, style.orientation, Anchor::CENTER);
+    }
+    transition::insert_transition(&mut entity, style.transition, config, direction.vector());
+    effects::insert_effects(&mut entity, &style.effects);
+    true
+}
+
+// レイアウト後の実際の大きさから開始位置・スクロール速度を決める
+#[allow(clippy::type_complexity)]
+pub fn measure_text_system(
+    mut cmds: Commands,
+    mut regions: ResMut<Regions>,
+    mut query: Query<
+        (
+            Entity,
+            &PendingLayout,
+            &TextLayoutInfo,
+            Option<&EntryImage>,
+            Option<&RegionId>,
+            &ScrollDirection,
+            &mut Transform,
+            Option<&mut LoopingText>,
+        ),
+        (With<Showing>, Without<FontFallbackPending>),
+    >,
+) {
+    for (entity, pending, info, image, region, direction, mut transform, looping_text) in &mut query {
+        let region = regions.get_mut(region.copied().unwrap_or_default());
+        // フォント・画像の読み込み中はまだ大きさが分からない
+        let Some(rect) = images::content_rect(info, None, image) else {
+            continue;
+        };
+        // 進行方向のテキスト（と画像）の長さと画面の長さ
+        let text_length = direction.along(rect.size());
+        let screen_length = direction.along(region.size());
+        let text_offset = crate::text::calc_text_offset(text_length, screen_length);
+
+        let start = match looping_text {
+            Some(mut looping_text) => {
+                looping_text.text_length = text_length;
+                looping_text.start = -direction.vector() * text_offset;
+                -direction.vector() * (screen_length / 2.0 + text_length / 2.0 + 50.0)
+            }
+            None => {
+                region.speed.speed = crate::text::calc_speed(text_length, &pending.duration, screen_length);
+                -direction.vector() * text_offset
+            }
+        };
+        transform.translation = start.extend(transform.translation.z);
+        cmds.entity(entity).remove::<PendingLayout>();
     }
 }
 
 pub fn spawn_static_text(
     cmds: &mut Commands,
     text: &str,
-    text_font: TextFont,
+    style: &EntryStyle,
+    region: &Region,
+    config: &Config,
+    context: &TemplateContext,
 ) {
-    cmds.spawn((
-        Text2d::new(text),
-        text_font,
-        TextColor(Color::Srgba(YELLOW_300)),
-        TextBackgroundColor(Color::Srgba(SLATE_900)),
-        Transform::from_xyz(0.0, 0.0, 0.0),
-        TextLayout::default(),
+    // 左寄せ・右寄せの場合は画面（領域）の端に揃える
+    let (anchor, x) = match style.align {
+        Some(TextAlign::Left) => (Anchor::CENTER_LEFT, -region.size().x / 2.0),
+        Some(TextAlign::Right) => (Anchor::CENTER_RIGHT, region.size().x / 2.0),
+        Some(TextAlign::Center) | None => (Anchor::CENTER, 0.0),
+    };
+    let mut entity = cmds.spawn((
+        fit::hidden_until_fitted(
+            transition::hidden_until_started(Transform::from_xyz(x, 0.0, 0.0), style.transition),
+            style.fit,
+        ),
+        TextLayout::new_with_justify(style.justify()),
+        anchor,
+        region.id,
+        region.id.layer(),
         Showing,
-    ))
-    .insert(NoFrustumCulling);
+    ));
+    // 折り返す場合は表示領域の幅を上限にする（縦書きは一文字ずつ改行済み）
+    if style.fit != AutoFit::Off && style.wrap && style.orientation == TextOrientation::Horizontal {
+        entity.insert(TextBounds::new_horizontal(region.safe_area(config).x));
+    }
+    let expanded = template::expand(text, context);
+    insert_spans(&mut entity, &style.spans(&expanded), style);
+    entity.insert(NoFrustumCulling);
+    if let Some(image) = &style.image {
+        images::insert_image(&mut entity, image, false, style.orientation, anchor);
+    }
+    // 時刻などを表示し続けるため、静止テキストは毎秒展開し直す
+    if template::has_placeholders(text) {
+        entity.insert(TextTemplate {
+            template: text.to_string(),
+            entry: context.entry.clone(),
+            rendered: expanded,
+        });
+    }
+    transition::insert_transition(&mut entity, style.transition, config, style.direction.vector());
+    effects::insert_effects(&mut entity, &style.effects);
+}
+
+// 表示中のテキスト（代替フォントの選択や再展開で作り直すときに使う）
+#[derive(Component)]
+pub struct StyledText {
+    pub spans: Vec<Span>,
+    pub style: EntryStyle,
+}
+
+// インライン記法で分けたテキストを Text2d と子の TextSpan にする（既存の子は作り直す）
+//
+// 代替フォントがある場合は、フォントの読み込み後に fonts::font_fallback_system が作り直す
+pub fn insert_spans(entity: &mut EntityCommands, spans: &[Span], style: &EntryStyle) {
+    let style = &EntryStyle {
+        reveal: style.reveal.or(effects::initial_reveal(&style.effects)),
+        ..style.clone()
+    };
+    let runs: Vec<_> = spans.iter().map(|span| (span.clone(), None)).collect();
+    render_runs(entity, &runs, style);
+    entity.insert(StyledText {
+        spans: spans.to_vec(),
+        style: style.clone(),
+    });
+    if !style.fallback_fonts.is_empty() {
+        entity.insert(FontFallbackPending);
+    }
+}
+
+// (部分, フォント) の一覧を表示する（フォントが None の部分はエントリのフォント）
+pub fn render_runs(entity: &mut EntityCommands, runs: &[(Span, Option<Handle<Font>>)], style: &EntryStyle) {
+    entity.despawn_related::<Children>();
+    let runs: &[_] = &match style.reveal {
+        Some(reveal) => hide_after(runs, reveal),
+        None => runs.to_vec(),
+    };
+    // 内容が変わったら大きさを調整し直す
+    if style.fit != AutoFit::Off {
+        entity.insert(Fitting::new(style.fit, style.wrap, style.fit_scale));
+    }
+    // 記法を含まない場合は一つの Text2d のまま
+    if let [(span, None)] = runs
+        && span.is_plain()
+    {
+        entity.insert(text_bundle(Text2d::new(span.text.clone()), style, None));
+        return;
+    }
+    entity.insert(text_bundle(Text2d::default(), style, None));
+    entity.with_children(|parent| {
+        for (span, font) in runs {
+            let mut bundle = text_bundle(TextSpan::new(span.text.clone()), style, Some(span));
+            if let Some(font) = font {
+                bundle.1.font = font.clone();
+            }
+            parent.spawn(bundle);
+        }
+    });
+}
+
+// reveal 文字目以降を透明にする（配置は変わらないように文字は残す）
+fn hide_after(runs: &[(Span, Option<Handle<Font>>)], reveal: usize) -> Vec<(Span, Option<Handle<Font>>)> {
+    let mut result = Vec::new();
+    let mut remaining = reveal;
+    for (span, font) in runs {
+        let len = span.text.chars().count();
+        if remaining >= len {
+            remaining -= len;
+            result.push((span.clone(), font.clone()));
+            continue;
+        }
+        let split = span.text.char_indices().nth(remaining).map_or(span.text.len(), |(i, _)| i);
+        let (shown, hidden) = span.text.split_at(split);
+        if !shown.is_empty() {
+            result.push((Span { text: shown.to_string(), ..span.clone() }, font.clone()));
+        }
+        let hidden = Span {
+            text: hidden.to_string(),
+            color: Some(Color::NONE),
+            background: Some(Color::NONE),
+            ..span.clone()
+        };
+        result.push((hidden, font.clone()));
+        remaining = 0;
+    }
+    result
+}
+
+fn text_bundle<T: Component>(text: T, style: &EntryStyle, span: Option<&Span>) -> (T, TextFont, TextColor, TextBackgroundColor) {
+    let mut text_font = style.text_font.clone();
+    text_font.font_size *= style.fit_scale;
+    let mut color = style.color;
+    let mut background = style.background;
+    if let Some(span) = span {
+        text_font.font_size *= span.scale;
+        if span.bold {
+            text_font.font = style.bold_font.clone();
+        }
+        color = span.color.unwrap_or(color);
+        background = span.background.unwrap_or(background);
+    }
+    (text, text_font, TextColor(color), TextBackgroundColor(background))
 }
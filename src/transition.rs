@@ -0,0 +1,268 @@
+use bevy::{prelude::*, sprite::Anchor, text::TextLayoutInfo};
+use std::collections::HashMap;
+
+use crate::fit::Fitting;
+use crate::fonts::FontFallbackPending;
+use crate::images::{self, EntryImage};
+use crate::loader::{Config, TransitionKind};
+use crate::regions::{RegionId, Regions};
+use crate::text_spawner::PendingLayout;
+use crate::Showing;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionPhase {
+    In,
+    Shown,
+    Out,
+}
+
+// 表示中のエンティティの切り替え効果（countdown の FadeComponent を一般化したもの）
+#[derive(Component)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub duration: f32,
+    pub direction: Vec2, // スライドの向き
+    pub hold: Option<f32>, // 指定すると表示後この秒数で自動的に消える
+    pub phase: TransitionPhase,
+    pub elapsed: f32,
+    started: bool,
+    applied_offset: Vec2,
+    base_alpha: HashMap<Entity, (f32, f32)>, // 文字・背景の元の透明度
+    cover: Option<Entity>,
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, duration: f32, direction: Vec2) -> Self {
+        Self {
+            kind,
+            duration,
+            direction,
+            hold: None,
+            phase: TransitionPhase::In,
+            elapsed: 0.0,
+            started: false,
+            applied_offset: Vec2::ZERO,
+            base_alpha: HashMap::new(),
+            cover: None,
+        }
+    }
+
+    pub fn with_hold(mut self, hold: f32) -> Self {
+        self.hold = Some(hold);
+        self
+    }
+
+    // フェードで現れる・消える途中か（点滅など透明度を変える効果はその間止める）
+    pub fn is_fading(&self) -> bool {
+        self.kind == TransitionKind::Fade && self.phase != TransitionPhase::Shown
+    }
+
+    // 表示されている割合（0 = 見えない、1 = 通常の表示）
+    fn visible(&self) -> f32 {
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t); // smoothstep
+        match self.phase {
+            TransitionPhase::In => t,
+            TransitionPhase::Shown => 1.0,
+            TransitionPhase::Out => 1.0 - t,
+        }
+    }
+}
+
+// 切り替え効果を付ける（none の場合は付けず、消すときもすぐに削除する）
+pub fn insert_transition(entity: &mut EntityCommands, kind: TransitionKind, config: &Config, direction: Vec2) {
+    if kind != TransitionKind::None {
+        entity.insert(Transition::new(kind, config.transition_duration(), direction));
+    }
+}
+
+// 消えていく途中のエンティティ（Showing は外してある）
+#[derive(Component)]
+pub struct Leaving;
+
+// ワイプで文字を隠す背景色の板
+#[derive(Component)]
+pub struct WipeCover;
+
+// 切り替え効果が始まるまでは縮小して隠しておく（fit::hidden_until_fitted と同様）
+pub fn hidden_until_started(transform: Transform, kind: TransitionKind) -> Transform {
+    match kind {
+        TransitionKind::None => transform,
+        _ => transform.with_scale(Vec3::ZERO),
+    }
+}
+
+// 表示中のエンティティ（切り替え効果・領域）
+pub type ShowingQuery<'w, 's> = Query<'w, 's, (Entity, Option<&'static Transition>, Option<&'static RegionId>), With<Showing>>;
+
+// 領域に表示中のテキストを消す（切り替え効果があれば終わってから削除する）
+pub fn dismiss_all(cmds: &mut Commands, query: &ShowingQuery, region: RegionId) {
+    for (entity, transition, entity_region) in query.iter() {
+        if entity_region.copied().unwrap_or_default() != region {
+            continue;
+        }
+        match transition {
+            Some(transition) if transition.kind != TransitionKind::None => {
+                cmds.entity(entity).remove::<Showing>().insert(Leaving);
+            }
+            _ => {
+                cmds.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn transition_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    regions: Res<Regions>,
+    clear_color: Res<ClearColor>,
+    mut query: Query<(
+        Entity,
+        &mut Transition,
+        &mut Transform,
+        Option<&Children>,
+        Option<&TextLayoutInfo>,
+        Option<&Anchor>,
+        Option<&EntryImage>,
+        Option<&RegionId>,
+        Has<Leaving>,
+        Has<Fitting>,
+        Has<PendingLayout>,
+        Has<FontFallbackPending>,
+    )>,
+    mut colors: Query<(&mut TextColor, Option<&mut TextBackgroundColor>)>,
+    mut sprites: Query<&mut Sprite, Without<WipeCover>>,
+    mut covers: Query<(Entity, &mut Sprite, &mut Transform), (With<WipeCover>, Without<Transition>)>,
+) {
+    let mut live_covers = Vec::new();
+    for (entity, mut transition, mut transform, children, info, anchor, image, region, leaving, fitting, pending, fallback) in &mut query {
+        let region = region.copied().unwrap_or_default();
+        if transition.kind == TransitionKind::None {
+            continue;
+        }
+        // 大きさ・位置が決まるまでは始めない
+        if !transition.started {
+            // 表示が始まる前に消された場合（読み込み中に次のエントリに進んだなど）はすぐに削除する
+            if leaving {
+                cmds.entity(entity).despawn();
+                continue;
+            }
+            if fitting || pending || fallback {
+                continue;
+            }
+            transition.started = true;
+            transform.scale = Vec3::ONE;
+        }
+
+        if leaving && transition.phase != TransitionPhase::Out {
+            // 現れる途中の場合はその位置から消えていく
+            let visible = (transition.elapsed / transition.duration).clamp(0.0, 1.0);
+            transition.elapsed = match transition.phase {
+                TransitionPhase::In => transition.duration * (1.0 - visible),
+                _ => 0.0,
+            };
+            transition.phase = TransitionPhase::Out;
+        }
+
+        let was_shown = transition.phase == TransitionPhase::Shown;
+        transition.elapsed += time.delta_secs();
+        match transition.phase {
+            TransitionPhase::In if transition.elapsed >= transition.duration => {
+                transition.phase = TransitionPhase::Shown;
+                transition.elapsed = 0.0;
+            }
+            TransitionPhase::Shown if transition.hold.is_some_and(|hold| transition.elapsed >= hold) => {
+                transition.phase = TransitionPhase::Out;
+                transition.elapsed = 0.0;
+            }
+            _ => {}
+        }
+        // 表示中は元の状態に戻した後は何もしない（ワイプの板も削除する）
+        if was_shown && transition.phase == TransitionPhase::Shown {
+            transition.cover = None;
+            continue;
+        }
+        let visible = transition.visible();
+
+        match transition.kind {
+            TransitionKind::None => {}
+            TransitionKind::Fade => {
+                let targets = std::iter::once(entity).chain(children.into_iter().flat_map(|children| children.iter()));
+                for target in targets {
+                    // 画像
+                    if let Ok(mut sprite) = sprites.get_mut(target) {
+                        let base = *transition.base_alpha.entry(target).or_insert((sprite.color.alpha(), 0.0));
+                        sprite.color.set_alpha(base.0 * visible);
+                        continue;
+                    }
+                    let Ok((mut text_color, background)) = colors.get_mut(target) else {
+                        continue;
+                    };
+                    let mut background = background;
+                    let base = *transition.base_alpha.entry(target).or_insert_with(|| {
+                        (text_color.0.alpha(), background.as_ref().map_or(0.0, |b| b.0.alpha()))
+                    });
+                    text_color.0.set_alpha(base.0 * visible);
+                    if let Some(background) = &mut background {
+                        background.0.set_alpha(base.1 * visible);
+                    }
+                }
+            }
+            TransitionKind::Slide => {
+                // スクロールと両立させるため、前回ずらした分との差だけ動かす
+                let distance = regions.get(region).size().dot(transition.direction.abs());
+                let offset = match transition.phase {
+                    TransitionPhase::Out => transition.direction * distance * (1.0 - visible),
+                    _ => -transition.direction * distance * (1.0 - visible),
+                };
+                transform.translation += (offset - transition.applied_offset).extend(0.0);
+                transition.applied_offset = offset;
+            }
+            TransitionKind::Zoom => {
+                transform.scale = Vec3::new(visible, visible, 1.0);
+            }
+            TransitionKind::Flip => {
+                transform.scale = Vec3::new(visible, 1.0, 1.0);
+            }
+            TransitionKind::Wipe => {
+                let Some(rect) = info.and_then(|info| images::content_rect(info, anchor, image)) else {
+                    continue;
+                };
+                let cover = *transition.cover.get_or_insert_with(|| {
+                    let color = regions.get(region).background.unwrap_or(clear_color.0);
+                    cmds.spawn((Sprite::from_color(color, Vec2::ZERO), Transform::default(), region.layer(), WipeCover)).id()
+                });
+                live_covers.push(cover);
+                if let Ok((_, mut sprite, mut cover_transform)) = covers.get_mut(cover) {
+                    // 文字の領域のうち、見えていない部分を覆う
+                    let size = rect.size() * transform.scale.truncate();
+                    let center = transform.translation.truncate() + rect.center() * transform.scale.truncate();
+                    let width = size.x * (1.0 - visible);
+                    let x = match transition.phase {
+                        TransitionPhase::Out => center.x - size.x / 2.0 + width / 2.0,
+                        _ => center.x + size.x / 2.0 - width / 2.0,
+                    };
+                    sprite.custom_size = Some(Vec2::new(width, size.y));
+                    cover_transform.translation = Vec3::new(x, center.y, transform.translation.z + 1.0);
+                }
+            }
+        }
+
+        let finished = match transition.phase {
+            TransitionPhase::Out => transition.elapsed >= transition.duration,
+            _ => false,
+        };
+        if finished {
+            cmds.entity(entity).despawn();
+        }
+    }
+
+    // 対象がなくなった板を削除
+    for (cover, _, _) in &covers {
+        if !live_covers.contains(&cover) {
+            cmds.entity(cover).despawn();
+        }
+    }
+}